@@ -0,0 +1,217 @@
+use pest::Parser;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::runner::{self, Run, RunError};
+use crate::solution::{Solution, SOLUTIONS};
+use crate::util;
+use crate::util::parse::ParseError;
+
+#[derive(Parser)]
+#[grammar_inline = r##"
+    WHITESPACE = _{ " " | "\t" }
+    COMMENT = _{ "#" ~ (!NEWLINE ~ ANY)* }
+    number = @{ ASCII_DIGIT+ }
+    string = @{ (!("\"" | NEWLINE) ~ ANY)* }
+    integer = @{ "-"? ~ ASCII_DIGIT+ }
+    value = ${ ("\"" ~ string ~ "\"") | integer }
+    day = ${ "[day" ~ number ~ "]" }
+    part = ${ "part" ~ number }
+    answer = { part ~ "=" ~ value }
+    section = { day ~ (NEWLINE+ ~ answer)* }
+    input = _{ SOI ~ NEWLINE* ~ (section ~ NEWLINE*)* ~ EOI }
+"##]
+struct AnswersParser;
+
+/// Expected answers keyed by day and part, as stored in `answers.toml`:
+///
+/// ```toml
+/// [day1]
+/// part1 = 1688
+/// part2 = "1728"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Answers(BTreeMap<(u32, u32), String>);
+
+impl Answers {
+    pub fn parse(input: &str) -> Result<Answers, ParseError> {
+        let input_tokens = AnswersParser::parse(Rule::input, input)?;
+
+        let mut answers = BTreeMap::new();
+
+        for token in input_tokens {
+            match token.as_rule() {
+                Rule::section => (),
+                Rule::EOI => continue,
+                _ => return Err(ParseError::unexpected_rule(&token)),
+            }
+
+            let mut section_tokens = token.into_inner();
+            let day_token = section_tokens.next().unwrap();
+            let day =
+                util::parse::token_to_number(day_token.into_inner().next().unwrap(), Rule::number)?;
+
+            for answer_token in section_tokens {
+                let (line, column) = answer_token.line_col();
+                let mut answer_tokens = answer_token.into_inner();
+                let part = util::parse::token_to_number(
+                    answer_tokens.next().unwrap().into_inner().next().unwrap(),
+                    Rule::number,
+                )?;
+                let value = answer_tokens.next().unwrap().into_inner().next().unwrap();
+
+                if answers
+                    .insert((day, part), value.as_str().to_string())
+                    .is_some()
+                {
+                    return Err(ParseError::Syntax {
+                        line,
+                        column,
+                        message: format!("duplicate answer for day {} part {}", day, part),
+                        rendered: None,
+                    });
+                }
+            }
+        }
+
+        Ok(Answers(answers))
+    }
+
+    pub fn get(&self, day: u32, part: u32) -> Option<&str> {
+        self.0.get(&(day, part)).map(String::as_str)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Status {
+    Pass,
+    Fail {
+        expected: String,
+        found: String,
+    },
+    /// The answers file has no entry for the solution.
+    MissingAnswer,
+    /// The puzzle input file could not be read.
+    MissingInput,
+    /// The puzzle input could not be parsed or solved.
+    Error(RunError),
+}
+
+impl Status {
+    pub fn is_pass(&self) -> bool {
+        *self == Status::Pass
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Status::Pass => write!(f, "pass"),
+            Status::Fail { expected, found } => {
+                write!(f, "fail (expected {}, found {})", expected, found)
+            }
+            Status::MissingAnswer => write!(f, "missing answer"),
+            Status::MissingInput => write!(f, "missing input"),
+            Status::Error(error) => write!(f, "error ({})", error),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Verification {
+    pub solution: &'static Solution,
+    pub status: Status,
+    pub run: Option<Run>,
+}
+
+fn verify_solution(
+    solution: &'static Solution,
+    answers: &Answers,
+    input_dir: &Path,
+) -> Verification {
+    let input_path = input_dir.join(format!("day{}.txt", solution.day));
+
+    let (status, run) = match (
+        answers.get(solution.day, solution.part),
+        fs::read_to_string(input_path),
+    ) {
+        (_, Err(_)) => (Status::MissingInput, None),
+        (None, Ok(_)) => (Status::MissingAnswer, None),
+        (Some(expected), Ok(input)) => {
+            match runner::run_solution(solution, input.trim_end_matches('\n')) {
+                Ok(run) if run.answer == expected => (Status::Pass, Some(run)),
+                Ok(run) => (
+                    Status::Fail {
+                        expected: expected.to_string(),
+                        found: run.answer.clone(),
+                    },
+                    Some(run),
+                ),
+                Err(error) => (Status::Error(error), None),
+            }
+        }
+    };
+
+    Verification {
+        solution,
+        status,
+        run,
+    }
+}
+
+/// Runs every registered solution on `input_dir/dayN.txt` and compares the
+/// result with `answers`.
+pub fn verify(answers: &Answers, input_dir: &Path) -> Vec<Verification> {
+    SOLUTIONS
+        .iter()
+        .map(|solution| verify_solution(solution, answers, input_dir))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let answers = Answers::parse(
+            "# comment\n[day1]\npart1 = 7\npart2 = \"5\" # trailing\n\n[day10]\npart1 = -3\n",
+        )
+        .unwrap();
+
+        assert_eq!(answers.get(1, 1), Some("7"));
+        assert_eq!(answers.get(1, 2), Some("5"));
+        assert_eq!(answers.get(10, 1), Some("-3"));
+        assert_eq!(answers.get(10, 2), None);
+    }
+
+    #[test]
+    fn parse_error() {
+        assert!(matches!(
+            Answers::parse("[day1]\npart1 = 7\npart1 = 8"),
+            Err(ParseError::Syntax { line: 3, .. })
+        ));
+        assert!(matches!(
+            Answers::parse("[day1]\npart1 7"),
+            Err(ParseError::Syntax { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn puzzle_answers() {
+        let input_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("input/2021");
+        let answers =
+            Answers::parse(&fs::read_to_string(input_dir.join("answers.toml")).unwrap()).unwrap();
+
+        for verification in verify(&answers, &input_dir) {
+            assert_eq!(
+                verification.status,
+                Status::Pass,
+                "{}",
+                verification.solution.name()
+            );
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use crate::runner::RunError;
+use crate::solution::Solution;
+
+/// Summary statistics of a set of timing samples.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stats {
+    pub min: Duration,
+    pub median: Duration,
+    pub mean: Duration,
+    pub stddev: Duration,
+}
+
+impl Stats {
+    /// Panics if `samples` is empty.
+    pub fn from_samples(samples: &[Duration]) -> Stats {
+        assert!(!samples.is_empty(), "no samples");
+
+        let mut sorted = samples.to_vec();
+        sorted.sort_unstable();
+
+        let count = sorted.len();
+        let median = if count.is_multiple_of(2) {
+            (sorted[count / 2 - 1] + sorted[count / 2]) / 2
+        } else {
+            sorted[count / 2]
+        };
+
+        let mean_secs = sorted.iter().map(Duration::as_secs_f64).sum::<f64>() / count as f64;
+        let variance = sorted
+            .iter()
+            .map(|sample| (sample.as_secs_f64() - mean_secs).powi(2))
+            .sum::<f64>()
+            / count as f64;
+
+        Stats {
+            min: sorted[0],
+            median,
+            mean: Duration::from_secs_f64(mean_secs),
+            stddev: Duration::from_secs_f64(variance.sqrt()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Benchmark {
+    pub solution: &'static Solution,
+    pub iterations: usize,
+    pub parse: Stats,
+    pub solve: Stats,
+}
+
+/// Times the generator and the solver of `solution` separately, `iterations`
+/// times each. The solver always runs on the output of one generator run.
+pub fn bench_solution(
+    solution: &'static Solution,
+    input: &str,
+    iterations: usize,
+) -> Result<Benchmark, RunError> {
+    let iterations = iterations.max(1);
+
+    let parsed = (solution.generator)(input)?;
+    (solution.solver)(parsed.as_ref())?;
+
+    let mut parse_samples = Vec::with_capacity(iterations);
+    for _ in 0..iterations {
+        let start = Instant::now();
+        black_box((solution.generator)(black_box(input))?);
+        parse_samples.push(start.elapsed());
+    }
+
+    let mut solve_samples = Vec::with_capacity(iterations);
+    for _ in 0..iterations {
+        let start = Instant::now();
+        black_box((solution.solver)(black_box(parsed.as_ref())))?;
+        solve_samples.push(start.elapsed());
+    }
+
+    Ok(Benchmark {
+        solution,
+        iterations,
+        parse: Stats::from_samples(&parse_samples),
+        solve: Stats::from_samples(&solve_samples),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solution;
+
+    #[test]
+    fn stats() {
+        let samples = [2, 4, 4, 4, 5, 5, 7, 9].map(Duration::from_millis);
+        let stats = Stats::from_samples(&samples);
+
+        assert_eq!(stats.min, Duration::from_millis(2));
+        assert_eq!(
+            stats.median,
+            Duration::from_millis(4) + Duration::from_micros(500)
+        );
+        assert_eq!(stats.mean.as_millis(), 5);
+        assert_eq!(stats.stddev.as_millis(), 2);
+    }
+
+    #[test]
+    fn bench() {
+        let solution = solution::find(1, 1, None).unwrap();
+        let benchmark = bench_solution(solution, "1\n2\n3", 3).unwrap();
+
+        assert_eq!(benchmark.iterations, 3);
+        assert!(benchmark.parse.min <= benchmark.parse.median);
+        assert!(bench_solution(solution, "1\nx", 3).is_err());
+    }
+}
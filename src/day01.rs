@@ -1,62 +1,113 @@
 use aoc_runner_derive::{aoc, aoc_generator};
-use std::num::ParseIntError;
 
-use pest::iterators::Pair;
 use pest::Parser;
+use std::collections::VecDeque;
+use std::io::BufRead;
+
+use crate::util;
+use crate::util::parse::ParseError;
+use crate::util::stream::{self, StreamError};
 
 #[derive(Parser)]
 #[grammar_inline = r#"
     number = { ASCII_DIGIT+ }
-    input = _{ (number ~ NEWLINE)+ }
+    input = _{ SOI ~ (number ~ NEWLINE?)+ ~ EOI }
+    reading = _{ SOI ~ number ~ EOI }
 "#]
 struct InputParser;
 
 #[aoc_generator(day1)]
-fn parse_input(input: &str) -> Result<Vec<u32>, ParseIntError> {
-    let input_tokens = InputParser::parse(Rule::input, input).unwrap_or_else(|e| panic!("{}", e));
-    input_tokens.map(|token| token_to_number(&token)).collect()
+pub fn parse_input(input: &str) -> Result<Vec<u32>, ParseError> {
+    let input_tokens = InputParser::parse(Rule::input, input)?;
+    input_tokens
+        .filter(|token| token.as_rule() != Rule::EOI)
+        .map(|token| util::parse::token_to_number(token, Rule::number))
+        .collect()
 }
 
-fn token_to_number(token: &Pair<Rule>) -> Result<u32, ParseIntError> {
-    let token_str = token.as_str();
-    token_str.parse::<u32>()
+fn parse_reading(line: &str) -> Result<u32, ParseError> {
+    let token = InputParser::parse(Rule::reading, line)?.next().unwrap();
+    util::parse::token_to_number(token, Rule::number)
 }
 
-#[aoc(day1, part1)]
-fn part1(input: &[u32]) -> usize {
-    let mut prev_number_opt: Option<u32> = None;
+/// Reads one depth per line from `reader` as they are needed.
+pub fn read_readings(reader: impl BufRead) -> impl Iterator<Item = Result<u32, StreamError>> {
+    stream::parse_lines(reader, parse_reading)
+}
+
+/// Number of times the sum of `window` consecutive readings is larger than
+/// the previous sum. Neighbouring sums share all but one reading, so only the
+/// last `window` readings are kept. Panics if `window` is 0.
+pub fn count_increases(readings: impl IntoIterator<Item = u32>, window: usize) -> usize {
+    assert!(window > 0, "window must hold at least one reading");
+
+    let mut recent = VecDeque::with_capacity(window);
     let mut increase_count: usize = 0;
 
-    for number in input {
-        if let Some(prev_number) = prev_number_opt {
-            increase_count += if prev_number < *number { 1 } else { 0 }
-        };
+    for reading in readings {
+        if recent.len() == window {
+            let leaving = recent.pop_front().unwrap();
+            increase_count += if leaving < reading { 1 } else { 0 };
+        }
 
-        prev_number_opt = Some(*number);
+        recent.push_back(reading);
     }
 
     increase_count
 }
 
+/// `count_increases` over the readings of `reader`, in constant memory.
+pub fn count_increases_streaming(
+    reader: impl BufRead,
+    window: usize,
+) -> Result<usize, StreamError> {
+    itertools::process_results(read_readings(reader), |readings| {
+        count_increases(readings, window)
+    })
+}
+
+#[aoc(day1, part1)]
+pub fn part1(input: &[u32]) -> usize {
+    count_increases(input.iter().copied(), 1)
+}
+
 const WINDOW_SIZE: usize = 3;
 
 #[aoc(day1, part2)]
-fn part2(input: &[u32]) -> usize {
-    let increase_count = input
-        .windows(WINDOW_SIZE)
-        .map(|window| window.iter().sum())
-        .collect::<Vec<u32>>()
-        .windows(2)
-        .map(|window| if window[0] < window[1] { 1 } else { 0 })
-        .sum();
-
-    increase_count
+pub fn part2(input: &[u32]) -> usize {
+    count_increases(input.iter().copied(), WINDOW_SIZE)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn input_parse() {
+        assert_eq!(parse_input("199\n200\n208"), Ok(vec![199, 200, 208]));
+    }
+
+    #[test]
+    fn input_parse_error() {
+        assert!(matches!(
+            parse_input("199\n2x0\n208"),
+            Err(ParseError::Syntax {
+                line: 2,
+                column: 2,
+                rendered: Some(rendered),
+                ..
+            }) if rendered.contains("2 | 2x0\n  |  ^---")
+        ));
+        assert!(matches!(
+            parse_input("199\n99999999999"),
+            Err(ParseError::Number {
+                line: 2,
+                column: 1,
+                ..
+            })
+        ));
+    }
+
     #[test]
     fn part1_example() {
         assert_eq!(
@@ -65,6 +116,23 @@ mod tests {
         );
     }
 
+    #[test]
+    fn streaming_example() {
+        let input = "199\n200\n208\n210\n200\n207\n240\n269\n260\n263\n";
+
+        assert_eq!(count_increases_streaming(input.as_bytes(), 1).unwrap(), 7);
+        assert_eq!(count_increases_streaming(input.as_bytes(), 3).unwrap(), 5);
+        assert_eq!(count_increases_streaming(input.as_bytes(), 10).unwrap(), 0);
+        assert!(matches!(
+            count_increases_streaming("199\n200\n2x8".as_bytes(), 1),
+            Err(StreamError::Parse(ParseError::Syntax {
+                line: 3,
+                column: 2,
+                ..
+            }))
+        ));
+    }
+
     #[test]
     fn part2_example() {
         assert_eq!(
@@ -1,91 +1,238 @@
 use aoc_runner_derive::{aoc, aoc_generator};
 
+use pest::iterators::Pair;
 use pest::Parser;
+use std::fmt;
+use std::io::BufRead;
+
+use crate::util;
+use crate::util::parse::ParseError;
+use crate::util::stream::{self, StreamError};
 
 #[derive(Parser)]
-#[grammar_inline = r#"
+#[grammar_inline = r##"
     up = { "up" }
     down = { "down" }
     forward = { "forward" }
-    number = { ASCII_DIGIT+ }
-    instruction = { (up | down | forward) ~ " "+ ~ number }
-    input = _{ (instruction ~ NEWLINE?)+ }
-"#]
+    back = { "back" }
+    number = { "-"? ~ ASCII_DIGIT+ }
+    instruction = { (up | down | forward | back) ~ " "+ ~ number }
+    comment = _{ "#" ~ (!NEWLINE ~ ANY)* }
+    entry = _{ " "* ~ instruction? ~ " "* ~ comment? }
+    input = _{ SOI ~ entry ~ (NEWLINE ~ entry)* ~ EOI }
+    line = _{ SOI ~ entry ~ EOI }
+"##]
 struct InputParser;
 
-#[derive(Debug)]
-enum Direction {
+/// A submarine command. Negative amounts reverse it, so `up -3` is `down 3`
+/// and `back 2` is `forward -2`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
     Up(isize),
     Down(isize),
     Forward(isize),
+    Back(isize),
 }
 
+fn instruction_to_direction(token: Pair<Rule>) -> Result<Direction, ParseError> {
+    if token.as_rule() != Rule::instruction {
+        return Err(ParseError::unexpected_rule(&token));
+    }
+
+    let mut inner_tokens = token.into_inner();
+    let direction_token = inner_tokens.next().unwrap();
+    let distance = util::parse::token_to_number(inner_tokens.next().unwrap(), Rule::number)?;
+
+    match direction_token.as_rule() {
+        Rule::up => Ok(Direction::Up(distance)),
+        Rule::down => Ok(Direction::Down(distance)),
+        Rule::forward => Ok(Direction::Forward(distance)),
+        Rule::back => Ok(Direction::Back(distance)),
+        _ => Err(ParseError::unexpected_rule(&direction_token)),
+    }
+}
+
+/// Parses one command per line. Blank lines and `#` comments are skipped.
 #[aoc_generator(day2)]
-fn parse_input(input: &str) -> Vec<Direction> {
-    let input_tokens = InputParser::parse(Rule::input, input).unwrap_or_else(|e| panic!("{}", e));
-
-    let mut directions = vec![];
-
-    for token in input_tokens {
-        match token.as_rule() {
-            Rule::instruction => {
-                let mut inner_tokens = token.into_inner();
-                let direction_token = inner_tokens.next().unwrap();
-                let distance_token = inner_tokens.next().unwrap();
-                assert_eq!(distance_token.as_rule(), Rule::number);
-                let distance = distance_token
-                    .as_str()
-                    .parse::<isize>()
-                    .unwrap_or_else(|e| panic!("{}", e));
-
-                match direction_token.as_rule() {
-                    Rule::up => directions.push(Direction::Up(distance)),
-                    Rule::down => directions.push(Direction::Down(distance)),
-                    Rule::forward => directions.push(Direction::Forward(distance)),
-                    _ => unreachable!(),
-                }
-            }
-            _ => unreachable!(),
+pub fn parse_input(input: &str) -> Result<Vec<Direction>, ParseError> {
+    let input_tokens = InputParser::parse(Rule::input, input)?;
+    input_tokens
+        .filter(|token| token.as_rule() != Rule::EOI)
+        .map(instruction_to_direction)
+        .collect()
+}
+
+fn parse_line(line: &str) -> Result<Option<Direction>, ParseError> {
+    InputParser::parse(Rule::line, line)?
+        .find(|token| token.as_rule() != Rule::EOI)
+        .map(instruction_to_direction)
+        .transpose()
+}
+
+/// Reads commands from `reader` as they are needed, skipping blank lines and
+/// comments like `parse_input`.
+pub fn read_directions(
+    reader: impl BufRead,
+) -> impl Iterator<Item = Result<Direction, StreamError>> {
+    stream::parse_lines(reader, parse_line).filter_map(Result::transpose)
+}
+
+/// How `up` and `down` are interpreted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Steering {
+    /// They change the depth directly (part 1).
+    Depth,
+    /// They change the aim and `forward` dives along it (part 2).
+    Aim,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Position {
+    pub horizontal: isize,
+    pub depth: isize,
+    /// Always 0 with `Steering::Depth`.
+    pub aim: isize,
+}
+
+#[derive(Debug)]
+pub enum CourseError {
+    /// The commands could not be read.
+    Stream(StreamError),
+    /// Following `direction` from `position` leaves the range of `isize`.
+    Overflow {
+        position: Position,
+        direction: Direction,
+    },
+    /// The horizontal position times the depth does not fit in an `isize`.
+    Product { position: Position },
+}
+
+impl fmt::Display for CourseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CourseError::Stream(error) => write!(f, "{}", error),
+            CourseError::Overflow {
+                position,
+                direction,
+            } => write!(f, "{:?} from {:?} overflows", direction, position),
+            CourseError::Product { position } => write!(
+                f,
+                "product of {} and {} overflows",
+                position.horizontal, position.depth
+            ),
         }
     }
-
-    directions
 }
 
-#[aoc(day2, part1)]
-fn part1(input: &[Direction]) -> isize {
-    let mut depth: isize = 0;
-    let mut horizontal: isize = 0;
-
-    for direction in input {
-        match direction {
-            Direction::Up(distance) => depth -= distance,
-            Direction::Down(distance) => depth += distance,
-            Direction::Forward(distance) => horizontal += distance,
+impl std::error::Error for CourseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CourseError::Stream(error) => Some(error),
+            _ => None,
         }
     }
+}
 
-    horizontal * depth
+impl From<StreamError> for CourseError {
+    fn from(error: StreamError) -> CourseError {
+        CourseError::Stream(error)
+    }
 }
 
-#[aoc(day2, part2)]
-fn part2(input: &[Direction]) -> isize {
-    let mut aim: isize = 0;
-    let mut depth: isize = 0;
-    let mut horizontal: isize = 0;
-
-    for direction in input {
-        match direction {
-            Direction::Up(distance) => aim -= distance,
-            Direction::Down(distance) => aim += distance,
-            Direction::Forward(distance) => {
-                horizontal += distance;
-                depth += aim * distance;
+impl Position {
+    pub fn product(&self) -> Result<isize, CourseError> {
+        self.horizontal
+            .checked_mul(self.depth)
+            .ok_or(CourseError::Product { position: *self })
+    }
+
+    /// Follows `direction`. The position is left unchanged when the result
+    /// would overflow.
+    pub fn apply(&mut self, direction: Direction, steering: Steering) -> Result<(), CourseError> {
+        *self = self
+            .moved(direction, steering)
+            .ok_or(CourseError::Overflow {
+                position: *self,
+                direction,
+            })?;
+
+        Ok(())
+    }
+
+    fn moved(mut self, direction: Direction, steering: Steering) -> Option<Position> {
+        match (direction, steering) {
+            (Direction::Up(distance), Steering::Depth) => {
+                self.depth = self.depth.checked_sub(distance)?
+            }
+            (Direction::Down(distance), Steering::Depth) => {
+                self.depth = self.depth.checked_add(distance)?
+            }
+            (Direction::Up(distance), Steering::Aim) => {
+                self.aim = self.aim.checked_sub(distance)?
+            }
+            (Direction::Down(distance), Steering::Aim) => {
+                self.aim = self.aim.checked_add(distance)?
+            }
+            (Direction::Forward(distance), _) => {
+                self.horizontal = self.horizontal.checked_add(distance)?;
+                self.depth = self.depth.checked_add(self.aim.checked_mul(distance)?)?;
+            }
+            // Subtracts rather than negating, which overflows for `isize::MIN`.
+            (Direction::Back(distance), _) => {
+                self.horizontal = self.horizontal.checked_sub(distance)?;
+                self.depth = self.depth.checked_sub(self.aim.checked_mul(distance)?)?;
             }
         }
+
+        Some(self)
     }
+}
+
+/// Positions along the course: the surface first, then the position after
+/// each direction. Lazy, so it can follow a stream of directions. Ends with
+/// an error at the first direction that overflows.
+pub fn trajectory(
+    directions: impl IntoIterator<Item = Direction>,
+    steering: Steering,
+) -> impl Iterator<Item = Result<Position, CourseError>> {
+    let start = Position::default();
+    let moves = directions
+        .into_iter()
+        .scan(Some(start), move |position, direction| {
+            let current = position.as_mut()?;
+            let moved = current.apply(direction, steering).map(|()| *current);
+            if moved.is_err() {
+                *position = None;
+            }
+            Some(moved)
+        });
+
+    std::iter::once(Ok(start)).chain(moves)
+}
+
+/// Final position after following every direction from the surface.
+pub fn follow(
+    directions: impl IntoIterator<Item = Direction>,
+    steering: Steering,
+) -> Result<Position, CourseError> {
+    trajectory(directions, steering).last().unwrap()
+}
+
+/// `follow` over the commands of `reader`, in constant memory.
+pub fn follow_streaming(reader: impl BufRead, steering: Steering) -> Result<Position, CourseError> {
+    itertools::process_results(read_directions(reader), |directions| {
+        follow(directions, steering)
+    })?
+}
 
-    horizontal * depth
+#[aoc(day2, part1)]
+pub fn part1(input: &[Direction]) -> Result<isize, CourseError> {
+    follow(input.iter().copied(), Steering::Depth)?.product()
+}
+
+#[aoc(day2, part2)]
+pub fn part2(input: &[Direction]) -> Result<isize, CourseError> {
+    follow(input.iter().copied(), Steering::Aim)?.product()
 }
 
 #[cfg(test)]
@@ -102,9 +249,139 @@ mod tests {
                 Direction::Up(3),
                 Direction::Down(8),
                 Direction::Forward(2)
-            ]),
+            ])
+            .unwrap(),
+            150
+        );
+    }
+
+    #[test]
+    fn streaming_example() {
+        let input = "forward 5\ndown 5\nforward 8\nup 3\ndown 8\nforward 2\n";
+
+        assert_eq!(
+            follow_streaming(input.as_bytes(), Steering::Depth)
+                .unwrap()
+                .product()
+                .unwrap(),
             150
         );
+        assert_eq!(
+            follow_streaming(input.as_bytes(), Steering::Aim).unwrap(),
+            Position {
+                horizontal: 15,
+                depth: 60,
+                aim: 10
+            }
+        );
+        assert!(matches!(
+            follow_streaming("up 1\nsideways 2".as_bytes(), Steering::Aim),
+            Err(CourseError::Stream(StreamError::Parse(
+                ParseError::Syntax { line: 2, .. }
+            )))
+        ));
+    }
+
+    #[test]
+    fn input_parse_extended() {
+        let input = "# course\n\nforward 5\n  back 2  # undo\nup -3\n\n";
+
+        assert_eq!(
+            parse_input(input),
+            Ok(vec![
+                Direction::Forward(5),
+                Direction::Back(2),
+                Direction::Up(-3)
+            ])
+        );
+        assert_eq!(parse_input(""), Ok(vec![]));
+        assert!(matches!(
+            parse_input("forward 5\nforward 5 5"),
+            Err(ParseError::Syntax { line: 2, .. })
+        ));
+        assert_eq!(
+            read_directions(input.as_bytes())
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap(),
+            parse_input(input).unwrap()
+        );
+    }
+
+    #[test]
+    fn trajectory_steering() {
+        let directions = [
+            Direction::Down(2),
+            Direction::Forward(3),
+            Direction::Back(1),
+            Direction::Up(-1),
+        ];
+        let position = |horizontal, depth, aim| Position {
+            horizontal,
+            depth,
+            aim,
+        };
+
+        assert_eq!(
+            trajectory(directions, Steering::Aim)
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap(),
+            vec![
+                position(0, 0, 0),
+                position(0, 0, 2),
+                position(3, 6, 2),
+                position(2, 4, 2),
+                position(2, 4, 3)
+            ]
+        );
+        assert_eq!(
+            follow(directions, Steering::Depth).unwrap(),
+            position(2, 3, 0)
+        );
+    }
+
+    #[test]
+    fn overflow() {
+        assert!(matches!(
+            part1(&[Direction::Up(isize::MIN)]),
+            Err(CourseError::Overflow {
+                direction: Direction::Up(isize::MIN),
+                ..
+            })
+        ));
+        assert!(matches!(
+            part2(&[Direction::Down(isize::MAX), Direction::Forward(2)]),
+            Err(CourseError::Overflow {
+                direction: Direction::Forward(2),
+                ..
+            })
+        ));
+        assert!(matches!(
+            part1(&[Direction::Forward(isize::MAX), Direction::Down(2)]),
+            Err(CourseError::Product { .. })
+        ));
+        // Going back by `isize::MIN` is fine as long as the result fits.
+        assert_eq!(
+            part1(&[
+                Direction::Forward(isize::MIN),
+                Direction::Back(isize::MIN),
+                Direction::Down(1)
+            ])
+            .unwrap(),
+            0
+        );
+
+        let steps = trajectory(
+            [
+                Direction::Down(1),
+                Direction::Up(isize::MIN),
+                Direction::Down(1),
+            ],
+            Steering::Depth,
+        );
+        assert_eq!(
+            steps.map(|step| step.is_ok()).collect::<Vec<_>>(),
+            vec![true, true, false]
+        );
     }
 
     #[test]
@@ -117,7 +394,8 @@ mod tests {
                 Direction::Up(3),
                 Direction::Down(8),
                 Direction::Forward(2)
-            ]),
+            ])
+            .unwrap(),
             900
         );
     }
@@ -1,141 +1,352 @@
 use aoc_runner_derive::{aoc, aoc_generator};
 
+use num::{BigUint, Zero};
 use pest::Parser;
+use std::cmp::Ordering;
+use std::fmt;
+
+use crate::util::grid::Grid;
+use crate::util::parse::ParseError;
 
 #[derive(Parser)]
 #[grammar_inline = r#"
     binary = { ("0" | "1") }
     report = { binary+ }
-    input = _{ (report ~ NEWLINE?)+ }
+    input = _{ SOI ~ (report ~ NEWLINE?)+ ~ EOI }
 "#]
 struct InputParser;
 
+/// Parses the diagnostic report into a grid of bits, one row per line with the
+/// most significant bit in column 0. The width is taken from the first line and
+/// every other line must have the same length.
 #[aoc_generator(day3)]
-fn parse_input(input: &str) -> Vec<u16> {
-    let input_tokens = InputParser::parse(Rule::input, input).unwrap_or_else(|e| panic!("{}", e));
-    input_tokens
-        .map(|token| u16::from_str_radix(token.as_str(), 2).unwrap_or_else(|e| panic!("{}", e)))
-        .collect()
+pub fn parse_input(input: &str) -> Result<Grid<bool>, ParseError> {
+    let input_tokens = InputParser::parse(Rule::input, input)?;
+
+    let mut width: Option<usize> = None;
+    let mut bits = vec![];
+
+    for token in input_tokens {
+        match token.as_rule() {
+            Rule::report => (),
+            Rule::EOI => continue,
+            _ => return Err(ParseError::unexpected_rule(&token)),
+        }
+
+        let report = token.as_str();
+        match width {
+            None => width = Some(report.len()),
+            Some(expected) if expected != report.len() => {
+                return Err(ParseError::GridShape {
+                    line: token.line_col().0,
+                    expected,
+                    found: report.len(),
+                })
+            }
+            Some(_) => (),
+        }
+
+        bits.extend(report.bytes().map(|bit| bit == b'1'));
+    }
+
+    Ok(Grid::from_vec(width.unwrap_or(0), bits))
 }
 
-#[aoc(day3, part1)]
-fn part1(input: &[u16]) -> usize {
-    let report_acc: u16 = input.iter().fold(0, |acc, report| acc | report);
-    let report_size: usize = 16 - report_acc.leading_zeros() as usize;
+fn bits_to_number(bits: impl Iterator<Item = bool>) -> BigUint {
+    bits.fold(BigUint::zero(), |acc, bit| (acc << 1u8) + u8::from(bit))
+}
 
-    let mut frequency = vec![0; report_size];
+/// Bit considered most common when a column has as many ones as zeros. The
+/// least common bit is always the other one, so ties pick the opposite bit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TiePolicy {
+    #[default]
+    One,
+    Zero,
+}
 
-    for report in input {
-        for i in 0..report_size {
-            frequency[report_size - i - 1] += if ((report >> i) & 0b1) == 0b1 { 1 } else { 0 };
+impl TiePolicy {
+    pub fn most_common(self, ones: usize, total: usize) -> bool {
+        match (ones * 2).cmp(&total) {
+            Ordering::Greater => true,
+            Ordering::Less => false,
+            Ordering::Equal => self == TiePolicy::One,
         }
     }
+}
 
-    let gamma_rate: u16 = frequency
-        .into_iter()
-        .map(|count| if count >= (input.len() / 2) { 1 } else { 0 })
-        .fold(0, |acc, val| (acc << 1) + val);
+/// Power consumption: the gamma rate (most common bit of each column) times
+/// the epsilon rate (least common bit).
+pub fn power_consumption(input: &Grid<bool>, policy: TiePolicy) -> BigUint {
+    let gamma_bits: Vec<bool> = input
+        .columns()
+        .map(|column| policy.most_common(column.filter(|&&bit| bit).count(), input.height()))
+        .collect();
 
-    let epsilon_rate: u16 = !gamma_rate & (!(0_u16) >> (16 - report_size));
+    let gamma_rate = bits_to_number(gamma_bits.iter().copied());
+    let epsilon_rate = bits_to_number(gamma_bits.iter().map(|&bit| !bit));
 
-    gamma_rate as usize * epsilon_rate as usize
+    gamma_rate * epsilon_rate
 }
 
-#[allow(clippy::ptr_arg)]
-fn common_bit_at(
-    values: &Vec<&u16>,
-    idx: usize,
-    predicate: fn(usize, usize) -> bool,
-) -> (u16, u16) {
-    let mask: u16 = 0b1 << idx;
+#[aoc(day3, part1)]
+pub fn part1(input: &Grid<bool>) -> BigUint {
+    power_consumption(input, TiePolicy::default())
+}
 
-    let mut bit_count: usize = 0;
-    let mut total_count: usize = 0;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rating {
+    /// Keeps reports with the most common bit.
+    OxygenGenerator,
+    /// Keeps reports with the least common bit.
+    Co2Scrubber,
+}
 
-    for &val in values {
-        bit_count += if val & mask != 0 { 1 } else { 0 };
-        total_count += 1;
-    }
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RatingError {
+    /// No candidate has the selected bit, which happens when distinct
+    /// candidates agree on the column and the least common bit is wanted.
+    NoCandidates { rating: Rating, column: usize },
+}
 
-    if predicate(bit_count, total_count) {
-        (mask, mask)
-    } else {
-        (0, mask)
+impl fmt::Display for RatingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RatingError::NoCandidates { rating, column } => write!(
+                f,
+                "no report left for the {:?} rating at bit {}",
+                rating, column
+            ),
+        }
     }
 }
 
-#[allow(clippy::ptr_arg)]
-fn oxygen_criteria(values: &Vec<&u16>, idx: usize) -> (u16, u16) {
-    common_bit_at(values, idx, |bit_count, total_count| {
-        bit_count * 2 >= total_count
-    })
-}
-
-#[allow(clippy::ptr_arg)]
-fn co2_criteria(values: &Vec<&u16>, idx: usize) -> (u16, u16) {
-    common_bit_at(values, idx, |bit_count, total_count| {
-        bit_count * 2 < total_count
-    })
-}
-
-fn filter_values(
-    values: Vec<&u16>,
-    idx: usize,
-    criteria: fn(&Vec<&u16>, usize) -> (u16, u16),
-) -> u16 {
-    let (target, mask) = criteria(&values, idx);
-    let next_values = Vec::from_iter(
-        values
-            .into_iter()
-            .filter(|&&value| (value & mask) == target),
-    );
-
-    match next_values.len() {
-        1 => *next_values[0],
-        0 => unreachable!(),
-        _ => filter_values(next_values, idx - 1, criteria),
+impl std::error::Error for RatingError {}
+
+/// Candidates left after filtering on one bit position.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceStep {
+    pub column: usize,
+    /// Indices of the surviving reports, in input order.
+    pub candidates: Vec<usize>,
+}
+
+/// Narrows the reports down column by column and returns the index of the
+/// report that determines `rating`. Every step is passed to `trace`.
+fn find_rating(
+    input: &Grid<bool>,
+    rating: Rating,
+    policy: TiePolicy,
+    mut trace: impl FnMut(TraceStep),
+) -> Result<usize, RatingError> {
+    let mut candidates = Vec::from_iter(0..input.height());
+
+    for column in 0..input.width() {
+        if candidates.len() <= 1 {
+            break;
+        }
+
+        let ones = candidates.iter().filter(|&&y| input[(column, y)]).count();
+        let most_common = policy.most_common(ones, candidates.len());
+        let target = match rating {
+            Rating::OxygenGenerator => most_common,
+            Rating::Co2Scrubber => !most_common,
+        };
+
+        let survivors = Vec::from_iter(
+            candidates
+                .iter()
+                .copied()
+                .filter(|&y| input[(column, y)] == target),
+        );
+        if survivors.is_empty() {
+            // Duplicates of one report are as good as a single report.
+            let first = input.row(candidates[0]);
+            if candidates.iter().all(|&y| input.row(y) == first) {
+                break;
+            }
+            return Err(RatingError::NoCandidates { rating, column });
+        }
+        candidates = survivors;
+
+        trace(TraceStep {
+            column,
+            candidates: candidates.clone(),
+        });
     }
+
+    Ok(candidates[0])
 }
 
-#[aoc(day3, part2)]
-fn part2(input: &[u16]) -> usize {
-    let report_acc: u16 = input.iter().fold(0, |acc, report| acc | report);
-    let report_size: usize = 16 - report_acc.leading_zeros() as usize;
-    let oxygen_rate = filter_values(
-        Vec::from_iter(input.iter()),
-        report_size - 1,
-        oxygen_criteria,
-    );
-    let co2_rate = filter_values(Vec::from_iter(input.iter()), report_size - 1, co2_criteria);
+pub fn rating(
+    input: &Grid<bool>,
+    rating: Rating,
+    policy: TiePolicy,
+) -> Result<BigUint, RatingError> {
+    let row = find_rating(input, rating, policy, |_| ())?;
+
+    Ok(bits_to_number(input.row(row).iter().copied()))
+}
+
+/// Surviving candidates after each bit position considered for `rating`.
+pub fn rating_trace(
+    input: &Grid<bool>,
+    rating: Rating,
+    policy: TiePolicy,
+) -> Result<Vec<TraceStep>, RatingError> {
+    let mut steps = vec![];
+    find_rating(input, rating, policy, |step| steps.push(step))?;
+
+    Ok(steps)
+}
+
+/// Life support rating: the oxygen generator rating times the CO2 scrubber
+/// rating.
+pub fn life_support_rating(input: &Grid<bool>, policy: TiePolicy) -> Result<BigUint, RatingError> {
+    let oxygen_rate = rating(input, Rating::OxygenGenerator, policy)?;
+    let co2_rate = rating(input, Rating::Co2Scrubber, policy)?;
 
-    oxygen_rate as usize * co2_rate as usize
+    Ok(oxygen_rate * co2_rate)
+}
+
+#[aoc(day3, part2)]
+pub fn part2(input: &Grid<bool>) -> Result<BigUint, RatingError> {
+    life_support_rating(input, TiePolicy::default())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const EXAMPLE_INPUT: &str = "\
+00100
+11110
+10110
+10111
+10101
+01111
+00111
+11100
+10000
+11001
+00010
+01010";
+
     #[test]
-    fn part1_example() {
-        assert_eq!(part1(&[0b10, 0b10]), 2);
+    fn input_parse() {
+        let input = parse_input("0010\n0001").unwrap();
+
+        assert_eq!(input.width(), 4);
+        assert_eq!(input.row(1), [false, false, false, true]);
+        assert_eq!(
+            parse_input("0010\n001\n0001"),
+            Err(ParseError::GridShape {
+                line: 2,
+                expected: 4,
+                found: 3
+            })
+        );
+    }
 
+    #[test]
+    fn part1_example() {
+        assert_eq!(part1(&parse_input("10\n10").unwrap()), BigUint::from(2u8));
+        // The width comes from the line length, not the highest set bit.
+        assert_eq!(
+            part1(&parse_input("010\n010").unwrap()),
+            BigUint::from(10u8)
+        );
         assert_eq!(
-            part1(&[
-                0b00100, 0b11110, 0b10110, 0b10111, 0b10101, 0b01111, 0b00111, 0b11100, 0b10000,
-                0b11001, 0b00010, 0b01010
-            ]),
-            198
+            part1(&parse_input(EXAMPLE_INPUT).unwrap()),
+            BigUint::from(198u8)
         );
     }
 
     #[test]
     fn part2_example() {
         assert_eq!(
-            part2(&[
-                0b00100, 0b11110, 0b10110, 0b10111, 0b10101, 0b01111, 0b00111, 0b11100, 0b10000,
-                0b11001, 0b00010, 0b01010
-            ]),
-            230
+            part2(&parse_input(EXAMPLE_INPUT).unwrap()),
+            Ok(BigUint::from(230u8))
+        );
+    }
+
+    #[test]
+    fn tie_policy() {
+        // Ones and zeros tie in both columns of the first pair.
+        let input = parse_input("10\n01\n11\n00").unwrap();
+
+        assert_eq!(
+            power_consumption(&input, TiePolicy::One),
+            BigUint::from(0u8)
+        );
+        assert_eq!(
+            life_support_rating(&input, TiePolicy::One),
+            Ok(BigUint::from(0u8))
+        );
+        assert_eq!(
+            rating(&input, Rating::OxygenGenerator, TiePolicy::Zero),
+            Ok(BigUint::from(0u8))
         );
+        assert_eq!(
+            rating(&input, Rating::Co2Scrubber, TiePolicy::Zero),
+            Ok(BigUint::from(3u8))
+        );
+        // An odd count is never a tie.
+        assert!(TiePolicy::Zero.most_common(2, 3));
+        assert!(!TiePolicy::One.most_common(1, 3));
+    }
+
+    #[test]
+    fn rating_errors() {
+        let input = parse_input("10\n11").unwrap();
+
+        assert_eq!(
+            part2(&input),
+            Err(RatingError::NoCandidates {
+                rating: Rating::Co2Scrubber,
+                column: 0
+            })
+        );
+        // Identical reports are not an error.
+        assert_eq!(
+            part2(&parse_input("10\n10").unwrap()),
+            Ok(BigUint::from(4u8))
+        );
+    }
+
+    #[test]
+    fn trace_example() {
+        let input = parse_input(EXAMPLE_INPUT).unwrap();
+
+        let steps = rating_trace(&input, Rating::Co2Scrubber, TiePolicy::One).unwrap();
+        assert_eq!(
+            steps,
+            vec![
+                TraceStep {
+                    column: 0,
+                    candidates: vec![0, 5, 6, 10, 11]
+                },
+                TraceStep {
+                    column: 1,
+                    candidates: vec![5, 11]
+                },
+                TraceStep {
+                    column: 2,
+                    candidates: vec![11]
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn wide_reports() {
+        let high = format!("{}{}", "1".repeat(50), "0".repeat(50));
+        let low = format!("{}{}", "0".repeat(50), "1".repeat(50));
+        let input = parse_input(&[&*high, &high, &high, &low].join("\n")).unwrap();
+
+        let low_rate = (BigUint::from(1u8) << 50u8) - 1u8;
+        let high_rate = &low_rate << 50u8;
+        assert_eq!(part1(&input), &high_rate * &low_rate);
+        assert_eq!(part2(&input), Ok(high_rate * low_rate));
     }
 }
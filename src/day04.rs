@@ -1,83 +1,150 @@
 use aoc_runner_derive::{aoc, aoc_generator};
 
+use pest::iterators::Pair;
 use pest::Parser;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::util;
+use crate::util::grid::Grid;
+use crate::util::parse::ParseError;
 
 #[derive(Parser)]
 #[grammar_inline = r#"
-    number = { ASCII_DIGIT{1,2} }
-    drawn_numbers = { (","? ~ number)+ ~ NEWLINE }
-    board = { (((" "+)? ~ number){5} ~ NEWLINE){5} }
-    input = _{ drawn_numbers ~ (NEWLINE? ~ board)+ }
+    number = { ASCII_DIGIT+ }
+    drawn_numbers = { number ~ ("," ~ number)* }
+    row = { " "* ~ number ~ (" "+ ~ number)* ~ " "* }
+    board = { row ~ (NEWLINE ~ row)* }
+    input = _{ SOI ~ drawn_numbers ~ NEWLINE+ ~ board ~ (NEWLINE{2,} ~ board)* ~ NEWLINE* ~ EOI }
 "#]
 struct InputParser;
 
-type Board = [u8; 25];
-type Mask = [bool; 25];
+/// Square board of any size.
+pub type Board = Grid<u16>;
 
-struct Bingo {
-    numbers: Vec<u8>,
+pub struct Bingo {
+    numbers: Vec<u16>,
     boards: Vec<Board>,
 }
 
+impl Bingo {
+    /// Side length shared by all boards.
+    pub fn size(&self) -> usize {
+        self.boards[0].width()
+    }
+}
+
+fn parse_board(token: Pair<Rule>, size: Option<usize>) -> Result<Board, ParseError> {
+    let board_token = token.clone();
+    let mut width: Option<usize> = None;
+    let mut cells = vec![];
+
+    for row_token in token.into_inner() {
+        if row_token.as_rule() != Rule::row {
+            return Err(ParseError::unexpected_rule(&row_token));
+        }
+
+        let (line, _column) = row_token.line_col();
+        let row_start = cells.len();
+
+        for number_token in row_token.into_inner() {
+            cells.push(util::parse::token_to_number(number_token, Rule::number)?);
+        }
+
+        let row_width = cells.len() - row_start;
+        let expected = width.or(size).unwrap_or(row_width);
+        if row_width != expected {
+            return Err(ParseError::GridShape {
+                line,
+                expected,
+                found: row_width,
+            });
+        }
+        width = Some(row_width);
+    }
+
+    let board = Grid::from_vec(width.unwrap(), cells);
+    if board.height() != board.width() {
+        return Err(ParseError::count(
+            &board_token,
+            board.width(),
+            board.height(),
+        ));
+    }
+
+    Ok(board)
+}
+
+/// Parses the drawn numbers and the boards. Boards are separated by blank
+/// lines, must be square and all have the size of the first one.
 #[aoc_generator(day4)]
-fn parse_input(input: &str) -> Bingo {
-    let input_tokens = InputParser::parse(Rule::input, input).unwrap_or_else(|e| panic!("{}", e));
+pub fn parse_input(input: &str) -> Result<Bingo, ParseError> {
+    let input_tokens = InputParser::parse(Rule::input, input)?;
 
     let mut numbers = vec![];
-    let mut boards = vec![];
+    let mut boards: Vec<Board> = vec![];
 
     for token in input_tokens {
         match token.as_rule() {
             Rule::drawn_numbers => {
                 for number_token in token.into_inner() {
-                    assert_eq!(number_token.as_rule(), Rule::number);
-                    numbers.push(
-                        number_token
-                            .as_str()
-                            .parse::<u8>()
-                            .unwrap_or_else(|e| panic!("{}", e)),
-                    );
+                    numbers.push(util::parse::token_to_number(number_token, Rule::number)?);
                 }
             }
             Rule::board => {
-                let mut index: usize = 0;
-                let mut board = [0; 25];
-
-                #[allow(clippy::explicit_counter_loop)]
-                for number_token in token.into_inner() {
-                    assert_eq!(number_token.as_rule(), Rule::number);
-                    board[index] = number_token
-                        .as_str()
-                        .parse::<u8>()
-                        .unwrap_or_else(|e| panic!("{}", e));
-                    index += 1;
-                }
-
-                boards.push(board);
+                let size = boards.first().map(Grid::width);
+                boards.push(parse_board(token, size)?);
             }
-            _ => unreachable!(),
+            Rule::EOI => (),
+            _ => return Err(ParseError::unexpected_rule(&token)),
         }
     }
 
-    Bingo { numbers, boards }
+    Ok(Bingo { numbers, boards })
+}
+
+/// Which lines of marked numbers win a board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rules {
+    pub rows: bool,
+    pub columns: bool,
+    /// Both main diagonals.
+    pub diagonals: bool,
+}
+
+impl Rules {
+    pub const STANDARD: Rules = Rules {
+        rows: true,
+        columns: true,
+        diagonals: false,
+    };
+
+    /// Cell indices of every winning line on a `size` × `size` board.
+    pub fn winning_lines(&self, size: usize) -> Vec<Vec<usize>> {
+        let mut lines = vec![];
+
+        if self.rows {
+            lines.extend((0..size).map(|y| (0..size).map(|x| y * size + x).collect()));
+        }
+        if self.columns {
+            lines.extend((0..size).map(|x| (0..size).map(|y| y * size + x).collect()));
+        }
+        if self.diagonals {
+            lines.push((0..size).map(|i| i * size + i).collect());
+            lines.push((0..size).map(|i| i * size + size - 1 - i).collect());
+        }
+
+        lines
+    }
 }
 
-const WINNING_COMBINATIONS: [[usize; 5]; 10] = [
-    // Rows
-    [0, 1, 2, 3, 4],
-    [5, 6, 7, 8, 9],
-    [10, 11, 12, 13, 14],
-    [15, 16, 17, 18, 19],
-    [20, 21, 22, 23, 24],
-    // Columns
-    [0, 5, 10, 15, 20],
-    [1, 6, 11, 16, 21],
-    [2, 7, 12, 17, 22],
-    [3, 8, 13, 18, 23],
-    [4, 9, 14, 19, 24],
-];
+impl Default for Rules {
+    fn default() -> Rules {
+        Rules::STANDARD
+    }
+}
 
-fn mark_number(board: &Board, marked: &mut [bool], drawn_number: u8) {
+fn mark_number(board: &Board, marked: &mut [bool], drawn_number: u16) {
     for (i, &number) in board.iter().enumerate() {
         if number == drawn_number {
             marked[i] = true;
@@ -86,24 +153,14 @@ fn mark_number(board: &Board, marked: &mut [bool], drawn_number: u8) {
     }
 }
 
-fn has_won(marked: &[bool]) -> bool {
-    for combination in WINNING_COMBINATIONS {
-        let mut has_won = true;
-        for index in combination {
-            if !marked[index] {
-                has_won = false;
-                break;
-            }
-        }
-        if has_won {
-            return true;
-        }
-    }
-
-    false
+/// First winning line, in the order of `winning_lines`, that is fully marked.
+fn winning_line<'a>(marked: &[bool], winning_lines: &'a [Vec<usize>]) -> Option<&'a Vec<usize>> {
+    winning_lines
+        .iter()
+        .find(|line| line.iter().all(|&index| marked[index]))
 }
 
-fn score(board: &Board, marked: &[bool], drawn_number: u8) -> usize {
+fn score(board: &Board, marked: &[bool], drawn_number: u16) -> usize {
     let mut sum: usize = 0;
 
     for (i, &number) in board.iter().enumerate() {
@@ -115,100 +172,339 @@ fn score(board: &Board, marked: &[bool], drawn_number: u8) -> usize {
     sum * drawn_number as usize
 }
 
-#[aoc(day4, part1)]
-fn part1(input: &Bingo) -> usize {
-    let mut marked: Vec<Mask> = vec![[false; 25]; input.boards.len()];
+/// A board completing a line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Win {
+    pub board: usize,
+    /// Position of the winning number among the drawn numbers.
+    pub draw_index: usize,
+    pub number: u16,
+    pub score: usize,
+    /// Cell indices of the completed line. When a draw completes several
+    /// lines at once, the first in `Rules::winning_lines` order.
+    pub line: Vec<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BingoError {
+    /// No board won with the drawn numbers.
+    NoWinner,
+    /// These boards never won, so there is no last winner.
+    NeverWon { boards: Vec<usize> },
+}
+
+impl fmt::Display for BingoError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BingoError::NoWinner => write!(f, "no board wins"),
+            BingoError::NeverWon { boards } => write!(f, "boards {:?} never win", boards),
+        }
+    }
+}
+
+impl std::error::Error for BingoError {}
 
-    for &drawn_number in &input.numbers {
-        for (board_nr, &board) in input.boards.iter().enumerate() {
-            mark_number(&board, &mut marked[board_nr], drawn_number);
-            if has_won(&marked[board_nr]) {
-                return score(&board, &marked[board_nr], drawn_number);
+/// Plays until every board has won or the numbers run out, and returns the
+/// wins in order. Boards winning on the same draw are ordered by index.
+pub fn play(input: &Bingo, rules: &Rules) -> Vec<Win> {
+    let winning_lines = rules.winning_lines(input.size());
+    let mut marked = vec![vec![false; input.size() * input.size()]; input.boards.len()];
+    let mut board_has_won = vec![false; input.boards.len()];
+    let mut wins = vec![];
+
+    for (draw_index, &drawn_number) in input.numbers.iter().enumerate() {
+        if wins.len() == input.boards.len() {
+            break;
+        }
+
+        for (board_nr, board) in input.boards.iter().enumerate() {
+            if board_has_won[board_nr] {
+                continue;
+            }
+            mark_number(board, &mut marked[board_nr], drawn_number);
+            if let Some(line) = winning_line(&marked[board_nr], &winning_lines) {
+                board_has_won[board_nr] = true;
+                wins.push(Win {
+                    board: board_nr,
+                    draw_index,
+                    number: drawn_number,
+                    score: score(board, &marked[board_nr], drawn_number),
+                    line: line.clone(),
+                });
             }
         }
     }
 
-    unreachable!();
+    wins
 }
 
-#[aoc(day4, part2)]
-fn part2(input: &Bingo) -> usize {
-    let mut marked: Vec<Mask> = vec![[false; 25]; input.boards.len()];
+/// Same result as `play`, but each draw only touches the cells holding the
+/// drawn number. Cells are found through a number → (board, cell) index and
+/// wins through per-line hit counters, so a draw costs O(occurrences) rather
+/// than a scan of every board.
+pub fn play_indexed(input: &Bingo, rules: &Rules) -> Vec<Win> {
+    let size = input.size();
+    let winning_lines = rules.winning_lines(size);
+
+    let mut lines_through = vec![vec![]; size * size];
+    for (line_nr, line) in winning_lines.iter().enumerate() {
+        for &cell in line {
+            lines_through[cell].push(line_nr);
+        }
+    }
+
+    // Like `mark_number`, only the first occurrence on a board counts.
+    let mut positions: HashMap<u16, Vec<(usize, usize)>> = HashMap::new();
+    for (board_nr, board) in input.boards.iter().enumerate() {
+        for (cell, &number) in board.iter().enumerate() {
+            let occurrences = positions.entry(number).or_default();
+            if occurrences.last().is_none_or(|&(last, _)| last != board_nr) {
+                occurrences.push((board_nr, cell));
+            }
+        }
+    }
+
+    let mut line_hits = vec![vec![0; winning_lines.len()]; input.boards.len()];
+    let mut unmarked_sums: Vec<usize> = input
+        .boards
+        .iter()
+        .map(|board| board.iter().map(|&number| number as usize).sum())
+        .collect();
     let mut board_has_won = vec![false; input.boards.len()];
+    let mut wins = vec![];
+
+    for (draw_index, &drawn_number) in input.numbers.iter().enumerate() {
+        if wins.len() == input.boards.len() {
+            break;
+        }
+
+        // A number drawn again marks cells that are already marked.
+        let Some(occurrences) = positions.remove(&drawn_number) else {
+            continue;
+        };
 
-    for &drawn_number in &input.numbers {
-        for (board_nr, &board) in input.boards.iter().enumerate() {
+        for (board_nr, cell) in occurrences {
             if board_has_won[board_nr] {
                 continue;
             }
-            mark_number(&board, &mut marked[board_nr], drawn_number);
-            if has_won(&marked[board_nr]) {
-                board_has_won[board_nr] = true;
-                if board_has_won.iter().all(|&val| val) {
-                    return score(&board, &marked[board_nr], drawn_number);
+            unmarked_sums[board_nr] -= drawn_number as usize;
+
+            let mut completed = None;
+            for &line_nr in &lines_through[cell] {
+                line_hits[board_nr][line_nr] += 1;
+                if line_hits[board_nr][line_nr] == size && completed.is_none() {
+                    completed = Some(line_nr);
                 }
             }
+
+            if let Some(line_nr) = completed {
+                board_has_won[board_nr] = true;
+                wins.push(Win {
+                    board: board_nr,
+                    draw_index,
+                    number: drawn_number,
+                    score: unmarked_sums[board_nr] * drawn_number as usize,
+                    line: winning_lines[line_nr].clone(),
+                });
+            }
         }
     }
 
-    unreachable!();
+    wins
+}
+
+/// Score of the first of `wins`.
+pub fn first_winner_score(wins: &[Win]) -> Result<usize, BingoError> {
+    wins.first()
+        .map(|win| win.score)
+        .ok_or(BingoError::NoWinner)
+}
+
+#[aoc(day4, part1, scan)]
+pub fn part1(input: &Bingo) -> Result<usize, BingoError> {
+    first_winner_score(&play(input, &Rules::STANDARD))
+}
+
+#[aoc(day4, part1, indexed)]
+pub fn part1_indexed(input: &Bingo) -> Result<usize, BingoError> {
+    first_winner_score(&play_indexed(input, &Rules::STANDARD))
+}
+
+/// Score of the last of `wins`. Fails unless every board of `input` won.
+pub fn last_winner_score(input: &Bingo, wins: &[Win]) -> Result<usize, BingoError> {
+    if wins.len() < input.boards.len() {
+        let mut boards = Vec::from_iter(0..input.boards.len());
+        boards.retain(|board| wins.iter().all(|win| win.board != *board));
+        return Err(BingoError::NeverWon { boards });
+    }
+
+    Ok(wins.last().unwrap().score)
+}
+
+#[aoc(day4, part2, scan)]
+pub fn part2(input: &Bingo) -> Result<usize, BingoError> {
+    last_winner_score(input, &play(input, &Rules::STANDARD))
+}
+
+#[aoc(day4, part2, indexed)]
+pub fn part2_indexed(input: &Bingo) -> Result<usize, BingoError> {
+    last_winner_score(input, &play_indexed(input, &Rules::STANDARD))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    const EXAMPLE_INPUT: &str = "\
+7,4,9,5,11,17,23,2,0,14,21,24,10,16,13,6,15,25,12,22,18,20,8,19,3,26,1
+
+22 13 17 11  0
+ 8  2 23  4 24
+21  9 14 16  7
+ 6 10  3 18  5
+ 1 12 20 15 19
+
+ 3 15  0  2 22
+ 9 18 13 17  5
+19  8  7 25 23
+20 11 10 24  4
+14 21 16 12  6
+
+14 21 17 24  4
+10 16 15  9 19
+18  8 23 26 20
+22 11 13  6  5
+ 2  0 12  3  7
+";
+
+    #[test]
+    fn input_parse() {
+        let bingo = parse_input(EXAMPLE_INPUT).unwrap();
+
+        assert_eq!(bingo.numbers.len(), 27);
+        assert_eq!(bingo.boards.len(), 3);
+        assert_eq!(bingo.size(), 5);
+        assert_eq!(bingo.boards[1][(4, 2)], 23);
+
+        let bingo = parse_input("100,7\n\n100 2\n  3 7\n\n1 2\n3 4").unwrap();
+        assert_eq!(bingo.numbers, vec![100, 7]);
+        assert_eq!(bingo.size(), 2);
+    }
+
+    #[test]
+    fn input_parse_error() {
+        assert!(matches!(
+            parse_input("1\n\n1 2\n3"),
+            Err(ParseError::GridShape {
+                line: 4,
+                expected: 2,
+                found: 1
+            })
+        ));
+        assert!(matches!(
+            parse_input("1\n\n1 2\n3 4\n5 6"),
+            Err(ParseError::Count {
+                expected: 2,
+                found: 3,
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse_input("1\n\n1 2\n3 4\n\n1 2 3\n4 5 6\n7 8 9"),
+            Err(ParseError::GridShape {
+                line: 6,
+                expected: 2,
+                found: 3
+            })
+        ));
+    }
 
     #[test]
     fn part1_example() {
+        let input = parse_input(EXAMPLE_INPUT).unwrap();
+
+        assert_eq!(part1(&input), Ok(4512));
+        assert_eq!(part1_indexed(&input), Ok(4512));
+    }
+
+    #[test]
+    fn part2_example() {
+        let input = parse_input(EXAMPLE_INPUT).unwrap();
+
+        assert_eq!(part2(&input), Ok(1924));
+        assert_eq!(part2_indexed(&input), Ok(1924));
+    }
+
+    #[test]
+    fn diagonal_rules() {
+        let bingo = parse_input("1,5,9,3\n\n1 2 3\n4 5 6\n7 8 9").unwrap();
+        let rules = Rules {
+            diagonals: true,
+            ..Rules::STANDARD
+        };
+
+        assert_eq!(rules.winning_lines(3).len(), 8);
+        assert_eq!(rules.winning_lines(3)[7], vec![2, 4, 6]);
+        // The main diagonal is complete on the third draw.
         assert_eq!(
-            part1(&Bingo {
-                numbers: vec![
-                    7, 4, 9, 5, 11, 17, 23, 2, 0, 14, 21, 24, 10, 16, 13, 6, 15, 25, 12, 22, 18,
-                    20, 8, 19, 3, 26, 1
-                ],
-                boards: vec![
-                    [
-                        22, 13, 17, 11, 0, 8, 2, 23, 4, 24, 21, 9, 14, 16, 7, 6, 10, 3, 18, 5, 1,
-                        12, 20, 15, 19
-                    ],
-                    [
-                        3, 15, 0, 2, 22, 9, 18, 13, 17, 5, 19, 8, 7, 25, 23, 20, 11, 10, 24, 4, 14,
-                        21, 16, 12, 6
-                    ],
-                    [
-                        14, 21, 17, 24, 4, 10, 16, 15, 9, 19, 18, 8, 23, 26, 20, 22, 11, 13, 6, 5,
-                        2, 0, 12, 3, 7
-                    ]
-                ]
-            }),
-            4512
+            first_winner_score(&play(&bingo, &rules)),
+            Ok((2 + 3 + 4 + 6 + 7 + 8) * 9)
         );
     }
 
     #[test]
-    fn part2_example() {
+    fn win_order() {
+        let wins = play(&parse_input(EXAMPLE_INPUT).unwrap(), &Rules::STANDARD);
+
         assert_eq!(
-            part2(&Bingo {
-                numbers: vec![
-                    7, 4, 9, 5, 11, 17, 23, 2, 0, 14, 21, 24, 10, 16, 13, 6, 15, 25, 12, 22, 18,
-                    20, 8, 19, 3, 26, 1
-                ],
-                boards: vec![
-                    [
-                        22, 13, 17, 11, 0, 8, 2, 23, 4, 24, 21, 9, 14, 16, 7, 6, 10, 3, 18, 5, 1,
-                        12, 20, 15, 19
-                    ],
-                    [
-                        3, 15, 0, 2, 22, 9, 18, 13, 17, 5, 19, 8, 7, 25, 23, 20, 11, 10, 24, 4, 14,
-                        21, 16, 12, 6
-                    ],
-                    [
-                        14, 21, 17, 24, 4, 10, 16, 15, 9, 19, 18, 8, 23, 26, 20, 22, 11, 13, 6, 5,
-                        2, 0, 12, 3, 7
-                    ]
-                ]
-            }),
-            1924
+            wins.iter()
+                .map(|win| (win.board, win.draw_index, win.number))
+                .collect::<Vec<_>>(),
+            vec![(2, 11, 24), (0, 13, 16), (1, 14, 13)]
         );
+        assert_eq!(wins[0].score, 4512);
+        assert_eq!(wins[0].line, vec![0, 1, 2, 3, 4]);
+        assert_eq!(wins[2].score, 1924);
+    }
+
+    #[test]
+    fn never_won() {
+        let bingo = parse_input("1,2,3\n\n1 2\n3 4\n\n5 6\n7 8").unwrap();
+
+        assert_eq!(part1(&bingo), Ok((3 + 4) * 2));
+        assert_eq!(part2(&bingo), Err(BingoError::NeverWon { boards: vec![1] }));
+        assert_eq!(
+            part1(&parse_input("9\n\n1 2\n3 4").unwrap()),
+            Err(BingoError::NoWinner)
+        );
+    }
+
+    #[test]
+    fn indexed_matches_scan() {
+        let mut rng = StdRng::seed_from_u64(4);
+
+        for _ in 0..200 {
+            let size = rng.gen_range(1..7);
+            let range = rng.gen_range(1..100);
+            let boards = (0..rng.gen_range(1..20))
+                .map(|_| {
+                    let cells = (0..size * size).map(|_| rng.gen_range(0..range)).collect();
+                    Grid::from_vec(size, cells)
+                })
+                .collect();
+            let numbers = (0..rng.gen_range(0..range * 2))
+                .map(|_| rng.gen_range(0..range))
+                .collect();
+            let bingo = Bingo { numbers, boards };
+            let rules = Rules {
+                rows: rng.gen(),
+                columns: rng.gen(),
+                diagonals: rng.gen(),
+            };
+
+            assert_eq!(play_indexed(&bingo, &rules), play(&bingo, &rules));
+        }
     }
 }
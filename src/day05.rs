@@ -1,30 +1,29 @@
 use aoc_runner_derive::{aoc, aoc_generator};
 
-use pest::iterators::Pair;
+use itertools::Either;
 use pest::Parser;
-use std::cmp;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+
+use crate::util;
+use crate::util::geometry::{self, Point, Segment};
+use crate::util::grid::Grid;
+use crate::util::parse::ParseError;
 
 #[derive(Parser)]
 #[grammar_inline = r#"
     number = { ASCII_DIGIT+ }
     line = { number ~ "," ~ number ~ " -> " ~ number ~ "," ~ number }
-    input = _{ (line ~ NEWLINE?)+  }
+    input = _{ SOI ~ (line ~ NEWLINE?)+ ~ EOI }
 "#]
 struct InputParser;
 
-type Line = [u16; 4];
-
-fn token_to_number(token: Pair<Rule>) -> u16 {
-    assert_eq!(token.as_rule(), Rule::number);
-    token
-        .as_str()
-        .parse::<u16>()
-        .unwrap_or_else(|e| panic!("{}", e))
-}
+/// Segment from `(x1, y1)` to `(x2, y2)` as `[x1, y1, x2, y2]`.
+pub type Line = [u32; 4];
 
 #[aoc_generator(day5)]
-fn parse_input(input: &str) -> Vec<Line> {
-    let input_tokens = InputParser::parse(Rule::input, input).unwrap_or_else(|e| panic!("{}", e));
+pub fn parse_input(input: &str) -> Result<Vec<Line>, ParseError> {
+    let input_tokens = InputParser::parse(Rule::input, input)?;
 
     let mut lines = vec![];
 
@@ -32,21 +31,23 @@ fn parse_input(input: &str) -> Vec<Line> {
         match token.as_rule() {
             Rule::line => {
                 let mut line_tokens = token.into_inner();
-                let x1 = token_to_number(line_tokens.next().unwrap());
-                let y1 = token_to_number(line_tokens.next().unwrap());
-                let x2 = token_to_number(line_tokens.next().unwrap());
-                let y2 = token_to_number(line_tokens.next().unwrap());
-                lines.push([x1, y1, x2, y2]);
+                let mut line = [0; 4];
+                for coordinate in line.iter_mut() {
+                    *coordinate =
+                        util::parse::token_to_number(line_tokens.next().unwrap(), Rule::number)?;
+                }
+                lines.push(line);
             }
-            _ => unreachable!(),
+            Rule::EOI => (),
+            _ => return Err(ParseError::unexpected_rule(&token)),
         }
     }
 
-    lines
+    Ok(lines)
 }
 
 fn largest_coordinate(lines: &[Line]) -> usize {
-    let mut largest: u16 = 0;
+    let mut largest: u32 = 0;
 
     for line in lines {
         for &coordinate in line {
@@ -59,164 +60,501 @@ fn largest_coordinate(lines: &[Line]) -> usize {
     largest as usize
 }
 
-fn coord_to_index(x: u16, y: u16, grid_size: usize) -> usize {
-    y as usize * grid_size + x as usize
+/// Direction of a line on a grid with y growing downwards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Orientation {
+    /// Also used for single points.
+    Horizontal,
+    Vertical,
+    /// 45° from top left to bottom right.
+    Diagonal,
+    /// 45° from bottom left to top right.
+    AntiDiagonal,
+    /// Any other slope.
+    Other,
 }
 
-fn draw_line(grid: &mut [u8], line: &Line, grid_size: usize) {
-    match line {
-        // Horizontal
-        [x1, y1, x2, y2] if y1 == y2 => {
-            let min = cmp::min(x1, x2);
-            let max = cmp::max(x1, x2);
+pub fn orientation(&[x1, y1, x2, y2]: &Line) -> Orientation {
+    if y1 == y2 {
+        Orientation::Horizontal
+    } else if x1 == x2 {
+        Orientation::Vertical
+    } else if x1.abs_diff(x2) != y1.abs_diff(y2) {
+        Orientation::Other
+    } else if (x1 < x2) == (y1 < y2) {
+        Orientation::Diagonal
+    } else {
+        Orientation::AntiDiagonal
+    }
+}
 
-            for x in *min..=*max {
-                let index = coord_to_index(x, *y1, grid_size);
-                grid[index] += 1;
-            }
+/// Set of orientations of the lines to draw.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Orientations {
+    pub horizontal: bool,
+    pub vertical: bool,
+    pub diagonal: bool,
+    pub anti_diagonal: bool,
+    pub other: bool,
+}
+
+impl Orientations {
+    pub const NONE: Orientations = Orientations {
+        horizontal: false,
+        vertical: false,
+        diagonal: false,
+        anti_diagonal: false,
+        other: false,
+    };
+    /// Horizontal and vertical lines (part 1).
+    pub const AXES: Orientations = Orientations {
+        horizontal: true,
+        vertical: true,
+        ..Orientations::NONE
+    };
+    /// Horizontal, vertical and 45° lines.
+    pub const STRAIGHT: Orientations = Orientations {
+        diagonal: true,
+        anti_diagonal: true,
+        ..Orientations::AXES
+    };
+    /// Every line (part 2, where strict rasterization rejects the lines at
+    /// other slopes).
+    pub const ALL: Orientations = Orientations {
+        other: true,
+        ..Orientations::STRAIGHT
+    };
+
+    pub fn contains(&self, orientation: Orientation) -> bool {
+        match orientation {
+            Orientation::Horizontal => self.horizontal,
+            Orientation::Vertical => self.vertical,
+            Orientation::Diagonal => self.diagonal,
+            Orientation::AntiDiagonal => self.anti_diagonal,
+            Orientation::Other => self.other,
         }
-        // Vertical
-        [x1, y1, x2, y2] if x1 == x2 => {
-            let min = cmp::min(y1, y2);
-            let max = cmp::max(y1, y2);
+    }
+}
 
-            for y in *min..=*max {
-                let index = coord_to_index(*x1, y, grid_size);
-                grid[index] += 1;
-            }
+/// How lines that are not horizontal, vertical or 45° diagonal are drawn.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Rasterization {
+    /// Such lines are an error.
+    #[default]
+    Strict,
+    /// Bresenham's algorithm: one point for every step along the longer axis.
+    Bresenham,
+    /// Only the lattice points exactly on the line.
+    Lattice,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LineError {
+    /// The line is not horizontal, vertical or 45° diagonal.
+    Slope { line: Line },
+}
+
+impl fmt::Display for LineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LineError::Slope {
+                line: [x1, y1, x2, y2],
+            } => write!(
+                f,
+                "line {},{} -> {},{} is not horizontal, vertical or diagonal",
+                x1, y1, x2, y2
+            ),
         }
-        // Other
-        _ => (),
     }
 }
 
-#[aoc(day5, part1)]
-fn part1(input: &[Line]) -> usize {
-    let grid_size = largest_coordinate(input) + 1;
-    let mut grid = vec![0; grid_size * grid_size];
+impl std::error::Error for LineError {}
+
+fn is_straight(&[x1, y1, x2, y2]: &Line) -> bool {
+    x1 == x2 || y1 == y2 || x1.abs_diff(x2) == y1.abs_diff(y2)
+}
+
+fn step(from: u32, to: u32) -> i64 {
+    (to > from) as i64 - (to < from) as i64
+}
 
-    for line in input {
-        draw_line(&mut grid, line, grid_size);
+/// Bresenham's line algorithm over all octants.
+struct Bresenham {
+    x: i64,
+    y: i64,
+    end: (i64, i64),
+    dx: i64,
+    dy: i64,
+    step: (i64, i64),
+    error: i64,
+    done: bool,
+}
+
+impl Bresenham {
+    fn new(&[x1, y1, x2, y2]: &Line) -> Bresenham {
+        let dx = x1.abs_diff(x2) as i64;
+        let dy = -(y1.abs_diff(y2) as i64);
+
+        Bresenham {
+            x: x1 as i64,
+            y: y1 as i64,
+            end: (x2 as i64, y2 as i64),
+            dx,
+            dy,
+            step: (step(x1, x2), step(y1, y2)),
+            error: dx + dy,
+            done: false,
+        }
     }
+}
+
+impl Iterator for Bresenham {
+    type Item = (u32, u32);
 
-    let mut count: usize = 0;
+    fn next(&mut self) -> Option<(u32, u32)> {
+        if self.done {
+            return None;
+        }
 
-    for cell in grid {
-        if cell >= 2 {
-            count += 1;
+        let point = (self.x as u32, self.y as u32);
+        if (self.x, self.y) == self.end {
+            self.done = true;
+            return Some(point);
         }
+
+        let double_error = 2 * self.error;
+        if double_error >= self.dy {
+            self.error += self.dy;
+            self.x += self.step.0;
+        }
+        if double_error <= self.dx {
+            self.error += self.dx;
+            self.y += self.step.1;
+        }
+
+        Some(point)
     }
+}
 
-    count
+/// Points covered by `line`, from `(x1, y1)` to `(x2, y2)`. All policies
+/// agree on horizontal, vertical and 45° diagonal lines.
+pub fn line_points(
+    line: &Line,
+    rasterization: Rasterization,
+) -> Result<impl Iterator<Item = (u32, u32)>, LineError> {
+    let &[x1, y1, x2, y2] = line;
+
+    match rasterization {
+        Rasterization::Strict if !is_straight(line) => Err(LineError::Slope { line: *line }),
+        Rasterization::Strict | Rasterization::Lattice => {
+            // Lattice points on the line are `gcd(|dx|, |dy|)` steps apart.
+            let steps = num::integer::gcd(x1.abs_diff(x2), y1.abs_diff(y2)) as i64;
+            let dx = (x2 as i64 - x1 as i64) / steps.max(1);
+            let dy = (y2 as i64 - y1 as i64) / steps.max(1);
+
+            Ok(Either::Left((0..=steps).map(move |i| {
+                ((x1 as i64 + i * dx) as u32, (y1 as i64 + i * dy) as u32)
+            })))
+        }
+        Rasterization::Bresenham => Ok(Either::Right(Bresenham::new(line))),
+    }
 }
 
-fn draw_line2(grid: &mut [u8], line: &Line, grid_size: usize) {
-    match line {
-        // Horizontal
-        [x1, y1, x2, y2] if y1 == y2 => {
-            let min = cmp::min(x1, x2);
-            let max = cmp::max(x1, x2);
+/// Whether `line` is drawn. Strict rasterization rejects lines at other
+/// slopes, but only those `orientations` would draw.
+fn is_drawn(
+    line: &Line,
+    orientations: Orientations,
+    rasterization: Rasterization,
+) -> Result<bool, LineError> {
+    if !orientations.contains(orientation(line)) {
+        return Ok(false);
+    }
+    if rasterization == Rasterization::Strict && !is_straight(line) {
+        return Err(LineError::Slope { line: *line });
+    }
 
-            for x in *min..=*max {
-                let index = coord_to_index(x, *y1, grid_size);
-                grid[index] += 1;
-            }
-        }
-        // Vertical
-        [x1, y1, x2, y2] if x1 == x2 => {
-            let min = cmp::min(y1, y2);
-            let max = cmp::max(y1, y2);
+    Ok(true)
+}
 
-            for y in *min..=*max {
-                let index = coord_to_index(*x1, y, grid_size);
-                grid[index] += 1;
-            }
+/// Number of lines covering each point, drawing only lines with one of
+/// `orientations`.
+pub fn coverage_grid(
+    lines: &[Line],
+    orientations: Orientations,
+    rasterization: Rasterization,
+) -> Result<Grid<u32>, LineError> {
+    let grid_size = largest_coordinate(lines) + 1;
+    let mut grid = Grid::new(grid_size, grid_size, 0);
+
+    for line in lines {
+        if !is_drawn(line, orientations, rasterization)? {
+            continue;
         }
-        // Diagonal
-        &[x1, y1, x2, y2] => {
-            let mut x = x1;
-            let mut y = y1;
 
-            loop {
-                let index = coord_to_index(x, y, grid_size);
-                grid[index] += 1;
-                if x == x2 && y == y2 {
-                    break;
-                }
-                if x1 < x2 {
-                    x += 1
-                } else {
-                    x -= 1
-                };
-                if y1 < y2 {
-                    y += 1
-                } else {
-                    y -= 1
-                };
-            }
+        for (x, y) in line_points(line, rasterization)? {
+            grid[(x as usize, y as usize)] += 1;
         }
     }
+
+    Ok(grid)
+}
+
+/// Number of points covered by at least `threshold` lines with one of
+/// `orientations`.
+pub fn count_overlaps_dense(
+    lines: &[Line],
+    orientations: Orientations,
+    rasterization: Rasterization,
+    threshold: u32,
+) -> Result<usize, LineError> {
+    let grid = coverage_grid(lines, orientations, rasterization)?;
+
+    Ok(grid.iter().filter(|&&cell| cell >= threshold).count())
+}
+
+#[aoc(day5, part1, dense)]
+pub fn part1(input: &[Line]) -> Result<usize, LineError> {
+    count_overlaps_dense(input, Orientations::AXES, Rasterization::Strict, 2)
 }
 
-#[aoc(day5, part2)]
-fn part2(input: &[Line]) -> usize {
-    let grid_size = largest_coordinate(input) + 1;
-    let mut grid = vec![0; grid_size * grid_size];
+#[aoc(day5, part2, dense)]
+pub fn part2(input: &[Line]) -> Result<usize, LineError> {
+    count_overlaps_dense(input, Orientations::ALL, Rasterization::Strict, 2)
+}
+
+/// Same as `count_overlaps_dense`, but counted in a hash map so memory grows
+/// with the number of covered points rather than the area.
+pub fn count_overlaps_sparse(
+    lines: &[Line],
+    orientations: Orientations,
+    rasterization: Rasterization,
+    threshold: u32,
+) -> Result<usize, LineError> {
+    let mut coverage: HashMap<(u32, u32), u32> = HashMap::new();
 
-    for line in input {
-        draw_line2(&mut grid, line, grid_size);
+    for line in lines {
+        if !is_drawn(line, orientations, rasterization)? {
+            continue;
+        }
+
+        for point in line_points(line, rasterization)? {
+            *coverage.entry(point).or_insert(0) += 1;
+        }
     }
 
-    let mut count: usize = 0;
+    Ok(coverage
+        .values()
+        .filter(|&&count| count >= threshold)
+        .count())
+}
 
-    for cell in grid {
-        if cell >= 2 {
-            count += 1;
+#[aoc(day5, part1, sparse)]
+pub fn part1_sparse(input: &[Line]) -> Result<usize, LineError> {
+    count_overlaps_sparse(input, Orientations::AXES, Rasterization::Strict, 2)
+}
+
+#[aoc(day5, part2, sparse)]
+pub fn part2_sparse(input: &[Line]) -> Result<usize, LineError> {
+    count_overlaps_sparse(input, Orientations::ALL, Rasterization::Strict, 2)
+}
+
+/// Points covered by two or more lines with one of `orientations`, with the
+/// indices into `lines` of all lines covering them, computed from exact
+/// segment intersections. Rasterization is always strict.
+pub fn vent_overlaps(
+    lines: &[Line],
+    orientations: Orientations,
+) -> Result<BTreeMap<Point, BTreeSet<usize>>, LineError> {
+    let mut line_indices = vec![];
+    let mut segments = vec![];
+
+    for (index, line) in lines.iter().enumerate() {
+        let &[x1, y1, x2, y2] = line;
+        if !is_drawn(line, orientations, Rasterization::Strict)? {
+            continue;
         }
+
+        let segment = Segment::new((x1 as i64, y1 as i64), (x2 as i64, y2 as i64)).unwrap();
+        line_indices.push(index);
+        segments.push(segment);
     }
 
-    count
+    Ok(geometry::overlaps(&segments)
+        .into_iter()
+        .map(|(point, covering)| {
+            let covering = covering.into_iter().map(|i| line_indices[i]).collect();
+            (point, covering)
+        })
+        .collect())
+}
+
+#[aoc(day5, part1, analytic)]
+pub fn part1_analytic(input: &[Line]) -> Result<usize, LineError> {
+    Ok(vent_overlaps(input, Orientations::AXES)?.len())
+}
+
+#[aoc(day5, part2, analytic)]
+pub fn part2_analytic(input: &[Line]) -> Result<usize, LineError> {
+    Ok(vent_overlaps(input, Orientations::ALL)?.len())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const EXAMPLE_INPUT: &str = "\
+0,9 -> 5,9
+8,0 -> 0,8
+9,4 -> 3,4
+2,2 -> 2,1
+7,0 -> 7,4
+6,4 -> 2,0
+0,9 -> 2,9
+3,4 -> 1,4
+0,0 -> 8,8
+5,5 -> 8,2";
+
     #[test]
     fn part1_example() {
+        let input = parse_input(EXAMPLE_INPUT).unwrap();
+
+        assert_eq!(part1(&input), Ok(5));
+        assert_eq!(part1_sparse(&input), Ok(5));
+        assert_eq!(part1_analytic(&input), Ok(5));
+    }
+
+    #[test]
+    fn part2_example() {
+        let input = parse_input(EXAMPLE_INPUT).unwrap();
+
+        assert_eq!(part2(&input), Ok(12));
+        assert_eq!(part2_sparse(&input), Ok(12));
+        assert_eq!(part2_analytic(&input), Ok(12));
+    }
+
+    #[test]
+    fn overlapping_vents() {
+        let overlaps =
+            vent_overlaps(&parse_input(EXAMPLE_INPUT).unwrap(), Orientations::STRAIGHT).unwrap();
+
+        assert_eq!(overlaps[&(0, 9)], BTreeSet::from([0, 6]));
+        assert_eq!(overlaps[&(4, 4)], BTreeSet::from([1, 2, 8]));
         assert_eq!(
-            part1(&[
-                [0, 9, 5, 9],
-                [8, 0, 0, 8],
-                [9, 4, 3, 4],
-                [2, 2, 2, 1],
-                [7, 0, 7, 4],
-                [6, 4, 2, 0],
-                [0, 9, 2, 9],
-                [3, 4, 1, 4],
-                [0, 0, 8, 8],
-                [5, 5, 8, 2]
-            ]),
-            5
+            overlaps
+                .iter()
+                .filter(|(_, covering)| covering.len() >= 3)
+                .map(|(&point, _)| point)
+                .collect::<Vec<_>>(),
+            vec![(4, 4), (6, 4)]
         );
     }
 
     #[test]
-    fn part2_example() {
+    fn sparse_huge_coordinates() {
+        let input = parse_input(
+            "4000000,3000000 -> 4000000,3000005\n\
+             3999998,3000002 -> 4000003,3000002\n\
+             3999999,2999999 -> 4000003,3000003",
+        )
+        .unwrap();
+
+        assert_eq!(part1_sparse(&input), Ok(1));
+        // The diagonal adds (4000000, 3000000) and (4000002, 3000002).
+        assert_eq!(part2_sparse(&input), Ok(3));
+
+        let stacked = vec![[7, 7, 7, 9]; 300];
+        assert_eq!(part1_sparse(&stacked), Ok(3));
+    }
+
+    #[test]
+    fn rasterization_policies() {
+        let points = |line: Line, rasterization| {
+            line_points(&line, rasterization).map(|points| points.collect::<Vec<_>>())
+        };
+
+        for rasterization in [
+            Rasterization::Strict,
+            Rasterization::Bresenham,
+            Rasterization::Lattice,
+        ] {
+            assert_eq!(
+                points([3, 1, 1, 3], rasterization),
+                Ok(vec![(3, 1), (2, 2), (1, 3)])
+            );
+            assert_eq!(points([4, 4, 4, 4], rasterization), Ok(vec![(4, 4)]));
+        }
+
+        assert_eq!(
+            points([0, 0, 4, 2], Rasterization::Strict),
+            Err(LineError::Slope { line: [0, 0, 4, 2] })
+        );
+        assert_eq!(
+            points([0, 0, 4, 2], Rasterization::Lattice),
+            Ok(vec![(0, 0), (2, 1), (4, 2)])
+        );
+        assert_eq!(
+            points([0, 0, 4, 2], Rasterization::Bresenham),
+            Ok(vec![(0, 0), (1, 1), (2, 1), (3, 2), (4, 2)])
+        );
+        assert_eq!(
+            points([1, 5, 0, 0], Rasterization::Bresenham),
+            Ok(vec![(1, 5), (1, 4), (1, 3), (0, 2), (0, 1), (0, 0)])
+        );
         assert_eq!(
-            part2(&[
-                [0, 9, 5, 9],
-                [8, 0, 0, 8],
-                [9, 4, 3, 4],
-                [2, 2, 2, 1],
-                [7, 0, 7, 4],
-                [6, 4, 2, 0],
-                [0, 9, 2, 9],
-                [3, 4, 1, 4],
-                [0, 0, 8, 8],
-                [5, 5, 8, 2]
-            ]),
-            12
+            points([0, 0, 3, 1], Rasterization::Lattice),
+            Ok(vec![(0, 0), (3, 1)])
         );
     }
+
+    #[test]
+    fn arbitrary_slopes() {
+        let input = parse_input("0,0 -> 4,2\n2,0 -> 2,3").unwrap();
+
+        assert_eq!(part2(&input), Err(LineError::Slope { line: [0, 0, 4, 2] }));
+        assert!(part2_analytic(&input).is_err());
+        assert_eq!(part1(&input), Ok(0));
+        assert_eq!(part1_sparse(&input), Ok(0));
+        assert_eq!(part1_analytic(&input), Ok(0));
+        assert_eq!(
+            count_overlaps_dense(&input, Orientations::ALL, Rasterization::Lattice, 2),
+            Ok(1)
+        );
+        assert_eq!(
+            count_overlaps_sparse(&input, Orientations::ALL, Rasterization::Bresenham, 2),
+            Ok(1)
+        );
+    }
+
+    #[test]
+    fn orientation_filters() {
+        let input = parse_input(EXAMPLE_INPUT).unwrap();
+
+        assert_eq!(orientation(&[0, 0, 0, 0]), Orientation::Horizontal);
+        assert_eq!(orientation(&[8, 8, 0, 0]), Orientation::Diagonal);
+        assert_eq!(orientation(&[8, 0, 0, 8]), Orientation::AntiDiagonal);
+        assert_eq!(orientation(&[0, 0, 2, 1]), Orientation::Other);
+
+        let diagonals = Orientations {
+            diagonal: true,
+            anti_diagonal: true,
+            ..Orientations::NONE
+        };
+        // Diagonals cross at (4, 4), (5, 3), (5, 5) and (6, 4).
+        for count in [count_overlaps_dense, count_overlaps_sparse] {
+            assert_eq!(count(&input, diagonals, Rasterization::Strict, 2), Ok(4));
+            assert_eq!(
+                count(&input, Orientations::STRAIGHT, Rasterization::Strict, 3),
+                Ok(2)
+            );
+            assert_eq!(
+                count(&input, Orientations::AXES, Rasterization::Strict, 1),
+                Ok(21)
+            );
+        }
+
+        let grid = coverage_grid(&input, Orientations::STRAIGHT, Rasterization::Strict).unwrap();
+        assert_eq!(grid[(4, 4)], 3);
+        assert_eq!(grid.width(), 10);
+    }
 }
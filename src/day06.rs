@@ -1,65 +1,290 @@
 use aoc_runner_derive::{aoc, aoc_generator};
 
-use pest::iterators::Pair;
+use num::{BigUint, One, Zero};
 use pest::Parser;
+use std::fmt;
+
+use crate::util;
+use crate::util::parse::ParseError;
 
 #[derive(Parser)]
 #[grammar_inline = r#"
     number = { ASCII_DIGIT+ }
-    input = _{ (","? ~ number)+ ~ NEWLINE?  }
+    input = _{ SOI ~ number ~ ("," ~ number)* ~ NEWLINE? ~ EOI }
 "#]
 struct InputParser;
 
-fn token_to_number(token: &Pair<Rule>) -> u8 {
-    assert_eq!(token.as_rule(), Rule::number);
-    token
-        .as_str()
-        .parse::<u8>()
-        .unwrap_or_else(|e| panic!("{}", e))
+#[aoc_generator(day6)]
+pub fn parse_input(input: &str) -> Result<Vec<u8>, ParseError> {
+    let input_tokens = InputParser::parse(Rule::input, input)?;
+    input_tokens
+        .filter(|token| token.as_rule() != Rule::EOI)
+        .map(|token| util::parse::token_to_number(token, Rule::number))
+        .collect()
 }
 
-#[aoc_generator(day6)]
-fn parse_input(input: &str) -> Vec<u8> {
-    let input_tokens = InputParser::parse(Rule::input, input).unwrap_or_else(|e| panic!("{}", e));
-    input_tokens.map(|token| token_to_number(&token)).collect()
+/// Lifecycle of a fish species: a fish spawns when its timer passes 0, after
+/// which its timer restarts at `reset_timer` and the newborn's starts at
+/// `newborn_timer`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FishModel {
+    pub reset_timer: u8,
+    pub newborn_timer: u8,
+}
+
+impl FishModel {
+    pub const LANTERNFISH: FishModel = FishModel {
+        reset_timer: 6,
+        newborn_timer: 8,
+    };
+}
+
+impl Default for FishModel {
+    fn default() -> FishModel {
+        FishModel::LANTERNFISH
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FishError {
+    /// The population no longer fits in a `usize` on `day`.
+    Overflow { day: usize },
+    /// A timer the lanternfish matrix has no bucket for.
+    Timer { timer: u8 },
 }
 
-fn count_fish(fishes: &[u8], days: usize) -> usize {
-    let mut count: [usize; 9] = [0; 9];
+impl fmt::Display for FishError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FishError::Overflow { day } => write!(f, "population overflows on day {}", day),
+            FishError::Timer { timer } => write!(f, "timer {} is above 8", timer),
+        }
+    }
+}
+
+impl std::error::Error for FishError {}
+
+/// Population at the start and after each of `days` days, so the series has
+/// `days + 1` entries. Every fish spawns at least once every `max_timer + 1`
+/// days, so the population overflows long before the series gets large.
+pub fn population_series(
+    fishes: &[u8],
+    days: usize,
+    model: &FishModel,
+) -> Result<Vec<usize>, FishError> {
+    let max_timer = fishes
+        .iter()
+        .copied()
+        .chain([model.reset_timer, model.newborn_timer])
+        .max()
+        .unwrap();
+    let mut count: Vec<usize> = vec![0; max_timer as usize + 1];
 
     for &fish in fishes {
         count[fish as usize] += 1;
     }
 
-    for _day in 0..days {
+    let mut series = vec![count.iter().sum()];
+
+    for day in 1..=days {
+        let overflow = FishError::Overflow { day };
+        let spawning = count[0];
         count.rotate_left(1);
-        count[6] += count[8];
+        count[max_timer as usize] = 0;
+        for timer in [model.newborn_timer, model.reset_timer] {
+            count[timer as usize] = count[timer as usize]
+                .checked_add(spawning)
+                .ok_or(overflow.clone())?;
+        }
+
+        let total = count
+            .iter()
+            .try_fold(0usize, |total, &fishes| total.checked_add(fishes))
+            .ok_or(overflow)?;
+        series.push(total);
     }
 
-    count.iter().sum()
+    Ok(series)
+}
+
+fn count_fish(fishes: &[u8], days: usize) -> Result<usize, FishError> {
+    Ok(*population_series(fishes, days, &FishModel::LANTERNFISH)?
+        .last()
+        .unwrap())
 }
 
-#[aoc(day6, part1)]
-fn part1(input: &[u8]) -> usize {
+#[aoc(day6, part1, rotate)]
+pub fn part1(input: &[u8]) -> Result<usize, FishError> {
     count_fish(input, 80)
 }
 
-#[aoc(day6, part2)]
-fn part2(input: &[u8]) -> usize {
+#[aoc(day6, part2, rotate)]
+pub fn part2(input: &[u8]) -> Result<usize, FishError> {
     count_fish(input, 256)
 }
 
+type Matrix<T> = [[T; 9]; 9];
+
+fn identity<T: Zero + One>() -> Matrix<T> {
+    std::array::from_fn(|i| std::array::from_fn(|j| if i == j { T::one() } else { T::zero() }))
+}
+
+/// One day of the bucket simulation as a matrix: `next = M * count`.
+fn transition<T: Zero + One>() -> Matrix<T> {
+    std::array::from_fn(|i| {
+        std::array::from_fn(|j| match (i, j) {
+            (8, 0) | (6, 0) => T::one(),
+            (i, j) if j == i + 1 => T::one(),
+            _ => T::zero(),
+        })
+    })
+}
+
+/// Matrix product with `reduce` applied after every multiplication and
+/// addition, which keeps modular arithmetic from overflowing.
+fn mat_mul<T, R>(a: &Matrix<T>, b: &Matrix<T>, reduce: &R) -> Matrix<T>
+where
+    T: Zero + One + Clone,
+    R: Fn(T) -> T,
+{
+    std::array::from_fn(|i| {
+        std::array::from_fn(|j| {
+            (0..9).fold(T::zero(), |acc, k| {
+                reduce(acc + reduce(a[i][k].clone() * b[k][j].clone()))
+            })
+        })
+    })
+}
+
+fn count_fish_with<T, R>(fishes: &[u8], days: u64, reduce: R) -> Result<T, FishError>
+where
+    T: Zero + One + Clone,
+    R: Fn(T) -> T,
+{
+    if let Some(&timer) = fishes.iter().find(|&&fish| fish > 8) {
+        return Err(FishError::Timer { timer });
+    }
+
+    let mut power = transition::<T>();
+    let mut result = identity::<T>();
+    let mut days = days;
+
+    while days > 0 {
+        if days & 1 == 1 {
+            result = mat_mul(&result, &power, &reduce);
+        }
+        power = mat_mul(&power, &power, &reduce);
+        days >>= 1;
+    }
+
+    // Every fish contributes the column of its timer to the total.
+    Ok(fishes.iter().fold(T::zero(), |acc, &fish| {
+        (0..9).fold(acc, |acc, i| reduce(acc + result[i][fish as usize].clone()))
+    }))
+}
+
+/// Exact lanternfish population after `days` days in O(log days) matrix
+/// products. Fails on timers above 8. The population grows by about 9% a
+/// day, so the result has roughly `days / 8` bits; use `count_fish_modular`
+/// for astronomically many days.
+pub fn count_fish_matrix(fishes: &[u8], days: u64) -> Result<BigUint, FishError> {
+    count_fish_with(fishes, days, |value| value)
+}
+
+/// Lanternfish population after `days` days modulo `modulus`.
+pub fn count_fish_modular(fishes: &[u8], days: u64, modulus: u64) -> Result<u64, FishError> {
+    let modulus = modulus as u128;
+    Ok(count_fish_with(fishes, days, |value: u128| value % modulus)? as u64)
+}
+
+#[aoc(day6, part1, matrix)]
+pub fn part1_matrix(input: &[u8]) -> Result<BigUint, FishError> {
+    count_fish_matrix(input, 80)
+}
+
+#[aoc(day6, part2, matrix)]
+pub fn part2_matrix(input: &[u8]) -> Result<BigUint, FishError> {
+    count_fish_matrix(input, 256)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn part1_example() {
-        assert_eq!(part1(&[3, 4, 3, 1, 2]), 5934);
+        assert_eq!(part1(&[3, 4, 3, 1, 2]), Ok(5934));
     }
 
     #[test]
     fn part2_example() {
-        assert_eq!(part2(&[3, 4, 3, 1, 2]), 26984457539);
+        assert_eq!(part2(&[3, 4, 3, 1, 2]), Ok(26984457539));
+        assert_eq!(
+            part2_matrix(&[3, 4, 3, 1, 2]),
+            Ok(BigUint::from(26984457539_u64))
+        );
+    }
+
+    #[test]
+    fn population_series_models() {
+        assert_eq!(
+            population_series(&[3, 4, 3, 1, 2], 5, &FishModel::default()),
+            Ok(vec![5, 5, 6, 7, 9, 10])
+        );
+
+        let model = FishModel {
+            reset_timer: 1,
+            newborn_timer: 2,
+        };
+        assert_eq!(
+            population_series(&[0], 6, &model),
+            Ok(vec![1, 2, 2, 3, 4, 5, 7])
+        );
+        assert_eq!(population_series(&[12], 3, &model), Ok(vec![1, 1, 1, 1]));
+    }
+
+    #[test]
+    fn population_series_overflow() {
+        assert_eq!(
+            population_series(&[3, 4, 3, 1, 2], usize::MAX, &FishModel::default()),
+            Err(FishError::Overflow { day: 490 })
+        );
+    }
+
+    #[test]
+    fn matrix_matches_simulation() {
+        for days in 0..300 {
+            assert_eq!(
+                count_fish_matrix(&[3, 4, 3, 1, 2, 0, 8], days),
+                Ok(BigUint::from(
+                    count_fish(&[3, 4, 3, 1, 2, 0, 8], days as usize).unwrap()
+                ))
+            );
+        }
+    }
+
+    #[test]
+    fn modular_huge_day_count() {
+        assert_eq!(
+            count_fish_modular(&[3, 4, 3, 1, 2], 256, 1_000_000_007),
+            Ok(26984457539_u64 % 1_000_000_007)
+        );
+        assert_eq!(
+            count_fish_modular(&[3, 4, 3, 1, 2], 1_000_000_000_000, 1_000_000_007),
+            Ok(995077479)
+        );
+        assert_eq!(count_fish_modular(&[3, 4, 3, 1, 2], 80, u64::MAX), Ok(5934));
+    }
+
+    #[test]
+    fn matrix_timer_range() {
+        assert_eq!(
+            count_fish_matrix(&[3, 9, 4], 10),
+            Err(FishError::Timer { timer: 9 })
+        );
+        assert_eq!(
+            count_fish_modular(&[12], 10, 7),
+            Err(FishError::Timer { timer: 12 })
+        );
     }
 }
@@ -1,77 +1,238 @@
 use aoc_runner_derive::{aoc, aoc_generator};
 
-use pest::iterators::Pair;
 use pest::Parser;
 use std::cmp;
 
+use crate::util;
+use crate::util::parse::ParseError;
+
 #[derive(Parser)]
 #[grammar_inline = r#"
     number = { ASCII_DIGIT+ }
-    input = _{ (","? ~ number)+ ~ NEWLINE?  }
+    input = _{ SOI ~ number ~ ("," ~ number)* ~ NEWLINE? ~ EOI }
 "#]
 struct InputParser;
 
-fn token_to_number(token: &Pair<Rule>) -> u16 {
-    assert_eq!(token.as_rule(), Rule::number);
-    token
-        .as_str()
-        .parse::<u16>()
-        .unwrap_or_else(|e| panic!("{}", e))
+#[aoc_generator(day7)]
+pub fn parse_input(input: &str) -> Result<Vec<u32>, ParseError> {
+    let input_tokens = InputParser::parse(Rule::input, input)?;
+    input_tokens
+        .filter(|token| token.as_rule() != Rule::EOI)
+        .map(|token| util::parse::token_to_number(token, Rule::number))
+        .collect()
 }
 
-#[aoc_generator(day7)]
-fn parse_input(input: &str) -> Vec<u16> {
-    let input_tokens = InputParser::parse(Rule::input, input).unwrap_or_else(|e| panic!("{}", e));
-    input_tokens.map(|token| token_to_number(&token)).collect()
+pub fn linear_cost(a: u32, b: u32) -> usize {
+    (cmp::max(a, b) - cmp::min(a, b)) as usize
 }
 
-fn calculate_fuel_cost(positions: &[u16], cost: fn(u16, u16) -> usize) -> usize {
-    let min: u16 = *positions.iter().min().unwrap();
-    let max: u16 = *positions.iter().max().unwrap();
+fn nonlinear_cost(a: u32, b: u32) -> usize {
+    let steps = linear_cost(a, b);
+    (1..=steps).sum()
+}
 
-    let mut min_distance: usize = usize::MAX;
+pub fn triangular_cost(a: u32, b: u32) -> usize {
+    let steps = linear_cost(a, b);
+    // Halve the even factor first so the product stays in range.
+    if steps.is_multiple_of(2) {
+        steps / 2 * (steps + 1)
+    } else {
+        steps.div_ceil(2) * steps
+    }
+}
 
-    for line in min..=max {
-        let mut distance: usize = 0;
+/// Summed in `u128`: a single crab can already cost close to `u64::MAX`.
+fn total_cost(positions: &[u32], line: u32, cost: fn(u32, u32) -> usize) -> u128 {
+    positions.iter().map(|&pos| cost(pos, line) as u128).sum()
+}
 
-        for &pos in positions {
-            distance += cost(pos, line);
-        }
+/// Total fuel needed to move every crab to `position`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Alignment {
+    pub position: u32,
+    pub cost: u128,
+}
 
-        if distance < min_distance {
-            min_distance = distance;
-        }
-    }
+/// Alignment cost for every position between the leftmost and the rightmost
+/// crab. `cost` is called with the crab's index in `positions`, its position
+/// and the target position, so models may treat crabs differently. Costs are
+/// summed in `u128` so that many expensive crabs cannot overflow.
+pub fn cost_curve<F>(positions: &[u32], cost: F) -> Vec<Alignment>
+where
+    F: Fn(usize, u32, u32) -> usize,
+{
+    let (min, max) = match (positions.iter().min(), positions.iter().max()) {
+        (Some(&min), Some(&max)) => (min, max),
+        _ => return vec![],
+    };
+
+    (min..=max)
+        .map(|line| Alignment {
+            position: line,
+            cost: positions
+                .iter()
+                .enumerate()
+                .map(|(crab, &pos)| cost(crab, pos, line) as u128)
+                .sum(),
+        })
+        .collect()
+}
 
-    min_distance
+/// Cheapest alignment on the cost curve, preferring the leftmost position on
+/// ties. `None` if there are no crabs.
+pub fn best_alignment<F>(positions: &[u32], cost: F) -> Option<Alignment>
+where
+    F: Fn(usize, u32, u32) -> usize,
+{
+    cost_curve(positions, cost)
+        .into_iter()
+        .min_by_key(|alignment| alignment.cost)
 }
 
-#[aoc(day7, part1)]
-fn part1(input: &[u16]) -> usize {
-    calculate_fuel_cost(input, |a, b| (cmp::max(a, b) - cmp::min(a, b)) as usize)
+fn calculate_fuel_cost(positions: &[u32], cost: fn(u32, u32) -> usize) -> u128 {
+    best_alignment(positions, |_crab, pos, line| cost(pos, line))
+        .unwrap()
+        .cost
 }
 
-#[aoc(day7, part2)]
-fn part2(input: &[u16]) -> usize {
-    fn nonlinear_cost(a: u16, b: u16) -> usize {
-        let steps = (cmp::max(a, b) - cmp::min(a, b)) as usize;
-        (1..=steps).sum()
-    }
+#[aoc(day7, part1, brute_force)]
+pub fn part1(input: &[u32]) -> u128 {
+    calculate_fuel_cost(input, linear_cost)
+}
+
+/// The sum of distances is minimised by any median of the positions.
+#[aoc(day7, part1, median)]
+pub fn part1_median(input: &[u32]) -> u128 {
+    let mut positions = input.to_vec();
+    let middle = positions.len() / 2;
+    let (_, &mut median, _) = positions.select_nth_unstable(middle);
 
+    total_cost(input, median, linear_cost)
+}
+
+#[aoc(day7, part2, brute_force)]
+pub fn part2(input: &[u32]) -> u128 {
     calculate_fuel_cost(input, nonlinear_cost)
 }
 
+/// The sum of triangular numbers is convex and its real-valued minimum lies
+/// within half a step of the mean, so only the integers around the mean need
+/// to be checked.
+#[aoc(day7, part2, mean)]
+pub fn part2_mean(input: &[u32]) -> u128 {
+    let min: u32 = *input.iter().min().unwrap();
+    let max: u32 = *input.iter().max().unwrap();
+    let sum: usize = input.iter().map(|&pos| pos as usize).sum();
+    let mean = (sum / input.len()) as u32;
+
+    (mean.saturating_sub(1)..=mean.saturating_add(2))
+        .map(|line| line.clamp(min, max))
+        .map(|line| total_cost(input, line, triangular_cost))
+        .min()
+        .unwrap()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
 
     #[test]
     fn part1_example() {
         assert_eq!(part1(&[16, 1, 2, 0, 4, 2, 7, 1, 2, 14]), 37);
+        assert_eq!(part1_median(&[16, 1, 2, 0, 4, 2, 7, 1, 2, 14]), 37);
     }
 
     #[test]
     fn part2_example() {
         assert_eq!(part2(&[16, 1, 2, 0, 4, 2, 7, 1, 2, 14]), 168);
+        assert_eq!(part2_mean(&[16, 1, 2, 0, 4, 2, 7, 1, 2, 14]), 168);
+    }
+
+    #[test]
+    fn alignment_models() {
+        let positions = [16, 1, 2, 0, 4, 2, 7, 1, 2, 14];
+
+        assert_eq!(
+            best_alignment(&positions, |_, a, b| linear_cost(a, b)),
+            Some(Alignment {
+                position: 2,
+                cost: 37
+            })
+        );
+        assert_eq!(
+            best_alignment(&positions, |_, a, b| triangular_cost(a, b)),
+            Some(Alignment {
+                position: 5,
+                cost: 168
+            })
+        );
+
+        let quadratic = |_, a, b| linear_cost(a, b).pow(2);
+        assert_eq!(best_alignment(&positions, quadratic).unwrap().position, 5);
+
+        let capped = |_, a, b| cmp::min(linear_cost(a, b), 3);
+        assert_eq!(best_alignment(&positions, capped).unwrap().cost, 15);
+
+        let weights = [100, 1, 1, 1, 1, 1, 1, 1, 1, 1];
+        let weighted = |crab: usize, a, b| weights[crab] * linear_cost(a, b);
+        assert_eq!(best_alignment(&positions, weighted).unwrap().position, 16);
+
+        let flat = |_, _, _| usize::MAX;
+        assert_eq!(
+            best_alignment(&positions, flat).unwrap().cost,
+            10 * usize::MAX as u128
+        );
+
+        let curve = cost_curve(&positions, |_, a, b| linear_cost(a, b));
+        assert_eq!(curve.len(), 17);
+        assert_eq!(
+            curve[1],
+            Alignment {
+                position: 1,
+                cost: 41
+            }
+        );
+        assert_eq!(best_alignment(&[], |_, a, b| linear_cost(a, b)), None);
+    }
+
+    #[test]
+    fn closed_form_matches_brute_force() {
+        let mut rng = StdRng::seed_from_u64(7);
+
+        for _ in 0..500 {
+            let len = rng.gen_range(1..40);
+            let range = rng.gen_range(1..500);
+            let positions: Vec<u32> = (0..len).map(|_| rng.gen_range(0..range)).collect();
+
+            assert_eq!(
+                part1_median(&positions),
+                part1(&positions),
+                "{:?}",
+                positions
+            );
+            assert_eq!(part2_mean(&positions), part2(&positions), "{:?}", positions);
+        }
+    }
+
+    #[test]
+    fn huge_coordinates() {
+        let input = parse_input("4000000000,100000,70000,3000000000").unwrap();
+
+        assert_eq!(input[0], 4_000_000_000);
+        assert_eq!(part1_median(&input), 6_999_830_000);
+        assert_eq!(part2_mean(&input), 6_374_702_507_337_415_000);
+        assert_eq!(triangular_cost(0, u32::MAX), 9_223_372_034_707_292_160);
+
+        // Each far crab costs about `u64::MAX / 2`, so the total needs more
+        // than 64 bits.
+        let extremes = [0, 0, 0, 0, u32::MAX, u32::MAX, u32::MAX, u32::MAX];
+        let middle = u32::MAX / 2;
+        assert_eq!(
+            part2_mean(&extremes),
+            4 * (triangular_cost(0, middle) + triangular_cost(middle, u32::MAX)) as u128
+        );
+        assert_eq!(part1_median(&extremes), 4 * u32::MAX as u128);
     }
 }
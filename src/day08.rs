@@ -4,6 +4,8 @@ use itertools::Itertools;
 use pest::iterators::Pair;
 use pest::Parser;
 
+use crate::util::parse::ParseError;
+
 #[derive(Parser)]
 #[grammar_inline = r#"
     segment = { ("a" | "b" | "c" | "d" | "e" | "f" | "g") }
@@ -11,22 +13,24 @@ use pest::Parser;
     patterns = { (" "? ~ digit){10} }
     output = { (" "? ~ digit){4} }
     entry = { patterns ~ " | " ~ output }
-    input = _{ NEWLINE? ~ ((" "+)? ~ entry ~ NEWLINE?)+ }
+    input = _{ SOI ~ NEWLINE? ~ ((" "+)? ~ entry ~ NEWLINE?)+ ~ " "* ~ EOI }
 "#]
 struct InputParser;
 
 #[derive(Debug, PartialEq, PartialOrd)]
-struct Entry {
+pub struct Entry {
     patterns: [u8; 10],
     output: [u8; 4],
 }
 
-fn digit_to_number(token: Pair<Rule>) -> u8 {
+fn digit_to_number(token: Pair<Rule>) -> Result<u8, ParseError> {
     let mut number: u8 = 0;
 
-    assert_eq!(token.as_rule(), Rule::digit);
+    if token.as_rule() != Rule::digit {
+        return Err(ParseError::unexpected_rule(&token));
+    }
+
     for segment_token in token.into_inner() {
-        assert_eq!(segment_token.as_rule(), Rule::segment);
         match segment_token.as_str() {
             "a" => number |= 0b1000000,
             "b" => number |= 0b100000,
@@ -35,44 +39,45 @@ fn digit_to_number(token: Pair<Rule>) -> u8 {
             "e" => number |= 0b100,
             "f" => number |= 0b10,
             "g" => number |= 0b1,
-            _ => unreachable!(),
+            _ => return Err(ParseError::unexpected_rule(&segment_token)),
         }
     }
 
-    number
+    Ok(number)
 }
 
 #[aoc_generator(day8)]
-fn parse_input(input: &str) -> Vec<Entry> {
-    let input_tokens = InputParser::parse(Rule::input, input).unwrap_or_else(|e| panic!("{}", e));
+pub fn parse_input(input: &str) -> Result<Vec<Entry>, ParseError> {
+    let input_tokens = InputParser::parse(Rule::input, input)?;
 
     let mut entries = vec![];
 
     for token in input_tokens {
-        assert_eq!(token.as_rule(), Rule::entry);
+        match token.as_rule() {
+            Rule::entry => (),
+            Rule::EOI => continue,
+            _ => return Err(ParseError::unexpected_rule(&token)),
+        }
 
         let mut entry_tokens = token.into_inner();
         let pattern_tokens = entry_tokens.next().unwrap();
-        assert_eq!(pattern_tokens.as_rule(), Rule::patterns);
-
         let output_tokens = entry_tokens.next().unwrap();
-        assert_eq!(output_tokens.as_rule(), Rule::output);
 
         let mut patterns = [0; 10];
         let mut output = [0; 4];
 
         for (i, digit_token) in pattern_tokens.into_inner().enumerate() {
-            patterns[i] = digit_to_number(digit_token);
+            patterns[i] = digit_to_number(digit_token)?;
         }
 
         for (i, digit_token) in output_tokens.into_inner().enumerate() {
-            output[i] = digit_to_number(digit_token);
+            output[i] = digit_to_number(digit_token)?;
         }
 
         entries.push(Entry { patterns, output })
     }
 
-    entries
+    Ok(entries)
 }
 
 fn is_unique_segment_count(digit: u8) -> bool {
@@ -81,7 +86,7 @@ fn is_unique_segment_count(digit: u8) -> bool {
 }
 
 #[aoc(day8, part1)]
-fn part1(input: &[Entry]) -> usize {
+pub fn part1(input: &[Entry]) -> usize {
     let mut count: usize = 0;
 
     for entry in input {
@@ -222,7 +227,7 @@ fn solve_output(solution: &[u8; 10], output: &[u8; 4]) -> usize {
 }
 
 #[aoc(day8, part2, naive)]
-fn part2(input: &[Entry]) -> usize {
+pub fn part2(input: &[Entry]) -> usize {
     let mut output: usize = 0;
 
     for entry in input {
@@ -264,7 +269,7 @@ fn generate_permutations() -> Vec<([u8; 10], [u8; 10])> {
         permutations.push(generate_digits(permutation));
     }
 
-    permutations.sort_unstable_by(|a, b| a.1.cmp(&b.1));
+    permutations.sort_unstable_by_key(|permutation| permutation.1);
     permutations
 }
 
@@ -283,7 +288,7 @@ fn search_solution(permutations: &[([u8; 10], [u8; 10])], target: &[u8; 10]) ->
 }
 
 #[aoc(day8, part2, permutation)]
-fn part2_permutation(input: &[Entry]) -> usize {
+pub fn part2_permutation(input: &[Entry]) -> usize {
     let mut output: usize = 0;
 
     let permutations = generate_permutations();
@@ -316,28 +321,31 @@ mod tests {
     #[test]
     fn input_parse() {
         assert_eq!(
-            parse_input(&"acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab | cdfeb fcadb cdfeb cdbaf"),
-            vec![
+            parse_input("acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab | cdfeb fcadb cdfeb cdbaf"),
+            Ok(vec![
                 Entry {
                     patterns: [0b1111111, 0b111110, 0b1011011, 0b1111010, 0b1101000, 0b1111110, 0b111111, 0b1100110, 0b1111101, 0b1100000],
                     output: [0b111110, 0b1111010, 0b111110, 0b1111010]
                 }
-            ]
+            ])
         );
     }
 
     #[test]
     fn part1_example() {
-        assert_eq!(part1(&parse_input(EXAMPLE_INPUT)), 26);
+        assert_eq!(part1(&parse_input(EXAMPLE_INPUT).unwrap()), 26);
     }
 
     #[test]
     fn part2_example() {
-        assert_eq!(part2(&parse_input(EXAMPLE_INPUT)), 61229);
+        assert_eq!(part2(&parse_input(EXAMPLE_INPUT).unwrap()), 61229);
     }
 
     #[test]
     fn part2_permutation_example() {
-        assert_eq!(part2_permutation(&parse_input(EXAMPLE_INPUT)), 61229);
+        assert_eq!(
+            part2_permutation(&parse_input(EXAMPLE_INPUT).unwrap()),
+            61229
+        );
     }
 }
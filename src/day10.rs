@@ -1,10 +1,22 @@
 use aoc_runner_derive::{aoc, aoc_generator};
 
+use pest::Parser;
 use phf::phf_map;
 
+use crate::util::parse::ParseError;
+
+#[derive(Parser)]
+#[grammar_inline = r#"
+    brace = _{ "(" | ")" | "[" | "]" | "{" | "}" | "<" | ">" }
+    line = _{ brace+ }
+    input = _{ SOI ~ (line ~ NEWLINE?)+ ~ EOI }
+"#]
+struct InputParser;
+
 #[aoc_generator(day10)]
-fn parse_input(input: &str) -> String {
-    input.to_string()
+pub fn parse_input(input: &str) -> Result<String, ParseError> {
+    InputParser::parse(Rule::input, input)?;
+    Ok(input.to_string())
 }
 
 const BRACE_MAP: phf::Map<char, char> = phf_map! {'(' => ')', '[' => ']', '{' => '}', '<' => '>'};
@@ -12,7 +24,7 @@ const ERROR_SCORE_MAP: phf::Map<char, usize> =
     phf_map! {')' => 3, ']' => 57, '}' => 1197, '>' => 25137};
 
 #[aoc(day10, part1)]
-fn part1(input: &str) -> usize {
+pub fn part1(input: &str) -> usize {
     let mut score: usize = 0;
 
     for line in input.lines() {
@@ -37,7 +49,7 @@ const AUTOCOMPLETE_SCORE_MAP: phf::Map<char, usize> =
     phf_map! {'(' => 1, '[' => 2, '{' => 3, '<' => 4};
 
 #[aoc(day10, part2)]
-fn part2(input: &str) -> usize {
+pub fn part2(input: &str) -> usize {
     let mut line_scores: Vec<usize> = vec![];
 
     for line in input.lines() {
@@ -61,8 +73,7 @@ fn part2(input: &str) -> usize {
 
         let mut line_score: usize = 0;
 
-        while !stack.is_empty() {
-            let c = stack.pop().unwrap();
+        while let Some(c) = stack.pop() {
             line_score *= 5;
             line_score += *AUTOCOMPLETE_SCORE_MAP.get(&c).unwrap();
         }
@@ -92,11 +103,11 @@ mod tests {
 
     #[test]
     fn part1_example() {
-        assert_eq!(part1(&parse_input(EXAMPLE_INPUT)), 26397);
+        assert_eq!(part1(&parse_input(EXAMPLE_INPUT).unwrap()), 26397);
     }
 
     #[test]
     fn part2_example() {
-        assert_eq!(part2(&parse_input(EXAMPLE_INPUT)), 288957);
+        assert_eq!(part2(&parse_input(EXAMPLE_INPUT).unwrap()), 288957);
     }
 }
@@ -1,147 +1,116 @@
 use aoc_runner_derive::{aoc, aoc_generator};
 
-use pest::Parser;
-use std::collections::HashSet;
-
-use crate::util;
-
-#[derive(Parser)]
-#[grammar_inline = r#"
-    cell = { ASCII_DIGIT }
-    input = _{ NEWLINE? ~ ((" "+)? ~ cell+ ~ NEWLINE?)+ }
-"#]
-struct InputParser;
-
-const ROW_SIZE: usize = 10;
-const COL_SIZE: usize = 10;
-const GRID_SIZE: usize = ROW_SIZE * COL_SIZE;
-
-type Grid = [u8; GRID_SIZE];
+use crate::util::grid::Grid;
+use crate::util::parse::ParseError;
 
 #[aoc_generator(day11)]
-fn parse_input(input: &str) -> Grid {
-    let input_tokens = InputParser::parse(Rule::input, input).unwrap_or_else(|e| panic!("{}", e));
-
-    let mut grid: Grid = [0; GRID_SIZE];
-
-    for (i, token) in input_tokens.into_iter().enumerate() {
-        grid[i] =
-            util::parse::token_to_number(token, Rule::cell).unwrap_or_else(|e| panic!("{}", e));
-    }
-
-    grid
+pub fn parse_input(input: &str) -> Result<Grid<u8>, ParseError> {
+    Grid::parse_digits(input)
 }
 
-fn index_to_coord(index: usize) -> (isize, isize) {
-    ((index % ROW_SIZE) as isize, (index / ROW_SIZE) as isize)
+/// Outcome of a single simulation step.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    /// Step number, starting from 1.
+    pub number: usize,
+    /// Coordinates of the octopuses that flashed, in the order they flashed.
+    pub flashes: Vec<(usize, usize)>,
+    /// Energy levels after the step.
+    pub grid: Grid<u8>,
 }
 
-fn coord_to_index(coordinate: (isize, isize)) -> usize {
-    coordinate.1 as usize * ROW_SIZE + coordinate.0 as usize
+impl Step {
+    pub fn is_synchronized(&self) -> bool {
+        self.grid.iter().all(|&energy| energy == 0)
+    }
 }
 
-fn neighbor_coordinates(coordinate: (isize, isize)) -> Vec<(isize, isize)> {
-    (-1..=1)
-        .flat_map(|y| (-1..=1).map(move |x| (coordinate.0 + x, coordinate.1 + y)))
-        .filter(|neighbor| *neighbor != coordinate)
-        .filter(|(x, y)| *x >= 0 && *y >= 0 && *x < ROW_SIZE as isize && *y < COL_SIZE as isize)
-        .collect()
+/// Octopus flash simulation. Iterating yields every step in turn.
+#[derive(Debug, Clone)]
+pub struct OctopusSim {
+    grid: Grid<u8>,
+    step_count: usize,
 }
 
-fn simulate_steps(grid: Grid, steps: usize, acc_count: usize) -> usize {
-    if steps == 0 {
-        return acc_count;
-    }
-
-    let mut next_grid: Grid = [0; GRID_SIZE];
-    next_grid.clone_from_slice(&grid);
-
-    let mut flashed_indices: HashSet<usize> = HashSet::new();
-    let mut to_propagate_indices = vec![];
-
-    for (idx, _level) in grid.iter().enumerate() {
-        next_grid[idx] += 1;
-        if next_grid[idx] > 9 {
-            flashed_indices.insert(idx);
-            to_propagate_indices.push(idx);
+impl OctopusSim {
+    pub fn new(grid: Grid<u8>) -> OctopusSim {
+        OctopusSim {
+            grid,
+            step_count: 0,
         }
     }
 
-    while !to_propagate_indices.is_empty() {
-        let idx = to_propagate_indices.pop().unwrap();
-        let flash_coord = index_to_coord(idx);
-        let neighbor_coords = neighbor_coordinates(flash_coord);
-
-        for neighbor_coord in neighbor_coords {
-            let neighbor_idx = coord_to_index(neighbor_coord);
-            if flashed_indices.contains(&neighbor_idx) {
-                continue;
-            }
-
-            next_grid[neighbor_idx] += 1;
-            if next_grid[neighbor_idx] > 9 {
-                flashed_indices.insert(neighbor_idx);
-                to_propagate_indices.push(neighbor_idx);
+    pub fn step(&mut self) -> Step {
+        let mut flashed = self.grid.map(|_| false);
+        let mut flashes = vec![];
+        let mut to_propagate_coords = vec![];
+
+        for coord in self.grid.coordinates() {
+            self.grid[coord] += 1;
+            if self.grid[coord] > 9 {
+                flashed[coord] = true;
+                flashes.push(coord);
+                to_propagate_coords.push(coord);
             }
         }
 
-        next_grid[idx] = 0;
-    }
-
-    simulate_steps(next_grid, steps - 1, acc_count + flashed_indices.len())
-}
-
-#[aoc(day11, part1)]
-fn part1(input: &Grid) -> usize {
-    simulate_steps(input.to_owned(), 100, 0)
-}
+        while let Some(coord) = to_propagate_coords.pop() {
+            for neighbor_coord in self.grid.neighbors8(coord.0, coord.1) {
+                if flashed[neighbor_coord] {
+                    continue;
+                }
+
+                self.grid[neighbor_coord] += 1;
+                if self.grid[neighbor_coord] > 9 {
+                    flashed[neighbor_coord] = true;
+                    flashes.push(neighbor_coord);
+                    to_propagate_coords.push(neighbor_coord);
+                }
+            }
 
-fn simulate_until_synchronized(grid: Grid, current_step: usize) -> usize {
-    let mut next_grid: Grid = [0; GRID_SIZE];
-    next_grid.clone_from_slice(&grid);
+            self.grid[coord] = 0;
+        }
 
-    let mut flashed_indices: HashSet<usize> = HashSet::new();
-    let mut to_propagate_indices = vec![];
+        self.step_count += 1;
 
-    for (idx, _level) in grid.iter().enumerate() {
-        next_grid[idx] += 1;
-        if next_grid[idx] > 9 {
-            flashed_indices.insert(idx);
-            to_propagate_indices.push(idx);
+        Step {
+            number: self.step_count,
+            flashes,
+            grid: self.grid.clone(),
         }
     }
+}
 
-    while !to_propagate_indices.is_empty() {
-        let idx = to_propagate_indices.pop().unwrap();
-        let flash_coord = index_to_coord(idx);
-        let neighbor_coords = neighbor_coordinates(flash_coord);
+impl Iterator for OctopusSim {
+    type Item = Step;
 
-        for neighbor_coord in neighbor_coords {
-            let neighbor_idx = coord_to_index(neighbor_coord);
-            if flashed_indices.contains(&neighbor_idx) {
-                continue;
-            }
+    fn next(&mut self) -> Option<Step> {
+        Some(self.step())
+    }
+}
 
-            next_grid[neighbor_idx] += 1;
-            if next_grid[neighbor_idx] > 9 {
-                flashed_indices.insert(neighbor_idx);
-                to_propagate_indices.push(neighbor_idx);
-            }
-        }
+fn simulate_steps(grid: Grid<u8>, steps: usize) -> usize {
+    OctopusSim::new(grid)
+        .take(steps)
+        .map(|step| step.flashes.len())
+        .sum()
+}
 
-        next_grid[idx] = 0;
-    }
+#[aoc(day11, part1)]
+pub fn part1(input: &Grid<u8>) -> usize {
+    simulate_steps(input.to_owned(), 100)
+}
 
-    if flashed_indices.len() >= GRID_SIZE {
-        current_step
-    } else {
-        simulate_until_synchronized(next_grid, current_step + 1)
-    }
+fn simulate_until_synchronized(grid: Grid<u8>) -> usize {
+    OctopusSim::new(grid)
+        .find(Step::is_synchronized)
+        .map(|step| step.number)
+        .unwrap()
 }
 
 #[aoc(day11, part2)]
-fn part2(input: &Grid) -> usize {
-    simulate_until_synchronized(input.to_owned(), 1)
+pub fn part2(input: &Grid<u8>) -> usize {
+    simulate_until_synchronized(input.to_owned())
 }
 
 #[cfg(test)]
@@ -161,13 +130,61 @@ mod tests {
         5283751526
     ";
 
+    const SMALL_EXAMPLE_INPUT: &str = r"
+        11111
+        19991
+        19191
+        19991
+        11111
+    ";
+
+    #[test]
+    fn input_parse_error() {
+        assert!(matches!(
+            parse_input("11111\n1999\n19191"),
+            Err(ParseError::GridShape { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn simulate_steps_small_example() {
+        assert_eq!(
+            simulate_steps(parse_input(SMALL_EXAMPLE_INPUT).unwrap(), 2),
+            9
+        );
+        assert_eq!(part2(&parse_input("999\n999").unwrap()), 1);
+    }
+
+    #[test]
+    fn step_small_example() {
+        let mut sim = OctopusSim::new(parse_input(SMALL_EXAMPLE_INPUT).unwrap());
+
+        let step = sim.step();
+        assert_eq!(step.number, 1);
+        assert_eq!(step.flashes.len(), 9);
+        assert_eq!(step.flashes[0], (1, 1));
+        assert_eq!(*step.flashes.last().unwrap(), (2, 2));
+        assert_eq!(
+            step.grid,
+            parse_input("34543\n40004\n50005\n40004\n34543").unwrap()
+        );
+
+        let step = sim.next().unwrap();
+        assert_eq!(step.number, 2);
+        assert!(step.flashes.is_empty());
+        assert_eq!(
+            step.grid,
+            parse_input("45654\n51115\n61116\n51115\n45654").unwrap()
+        );
+    }
+
     #[test]
     fn part1_example() {
-        assert_eq!(part1(&parse_input(EXAMPLE_INPUT)), 1656);
+        assert_eq!(part1(&parse_input(EXAMPLE_INPUT).unwrap()), 1656);
     }
 
     #[test]
     fn part2_example() {
-        assert_eq!(part2(&parse_input(EXAMPLE_INPUT)), 195);
+        assert_eq!(part2(&parse_input(EXAMPLE_INPUT).unwrap()), 195);
     }
 }
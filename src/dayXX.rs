@@ -3,31 +3,31 @@ use aoc_runner_derive::{aoc, aoc_generator};
 use pest::Parser;
 
 use crate::util;
+use crate::util::parse::ParseError;
 
 #[derive(Parser)]
 #[grammar_inline = r#"
     number = { ASCII_DIGIT+ }
-    input = _{ (number ~ NEWLINE?)+  }
+    input = _{ SOI ~ (number ~ NEWLINE?)+ ~ EOI }
 "#]
 struct InputParser;
 
 #[aoc_generator(day0)]
-fn parse_input(input: &str) -> Vec<u32> {
-    let input_tokens = InputParser::parse(Rule::input, input).unwrap_or_else(|e| panic!("{}", e));
+pub fn parse_input(input: &str) -> Result<Vec<u32>, ParseError> {
+    let input_tokens = InputParser::parse(Rule::input, input)?;
     input_tokens
-        .map(|token| {
-            util::parse::token_to_number(token, Rule::number).unwrap_or_else(|e| panic!("{}", e))
-        })
+        .filter(|token| token.as_rule() != Rule::EOI)
+        .map(|token| util::parse::token_to_number(token, Rule::number))
         .collect()
 }
 
 #[aoc(day0, part1)]
-fn part1(input: &[u32]) -> usize {
+pub fn part1(input: &[u32]) -> usize {
     0
 }
 
 #[aoc(day0, part2)]
-fn part2(input: &[u32]) -> usize {
+pub fn part2(input: &[u32]) -> usize {
     0
 }
 
@@ -5,6 +5,35 @@ extern crate pest_derive;
 
 mod util;
 
+pub use day01::{count_increases, count_increases_streaming, read_readings};
+pub use day02::{
+    follow, follow_streaming, read_directions, trajectory, CourseError, Direction, Position,
+    Steering,
+};
+pub use day03::{
+    life_support_rating, power_consumption, rating, rating_trace, Rating, RatingError, TiePolicy,
+    TraceStep,
+};
+pub use day04::{
+    first_winner_score, last_winner_score, parse_input as parse_bingo, play, play_indexed, Bingo,
+    BingoError, Board, Rules, Win,
+};
+pub use day05::{
+    count_overlaps_dense, count_overlaps_sparse, coverage_grid, line_points, orientation,
+    parse_input as parse_vents, vent_overlaps, Line, LineError, Orientation, Orientations,
+    Rasterization,
+};
+pub use day06::{
+    count_fish_matrix, count_fish_modular, parse_input as parse_lanternfish, population_series,
+    FishError, FishModel,
+};
+pub use day07::{best_alignment, cost_curve, linear_cost, triangular_cost, Alignment};
+pub use solution::{Solution, SOLUTIONS};
+pub use util::geometry::{Point, Segment};
+pub use util::grid::Grid;
+pub use util::parse::ParseError;
+pub use util::stream::StreamError;
+
 mod day01;
 mod day02;
 mod day03;
@@ -17,6 +46,12 @@ mod day09;
 mod day10;
 mod day11;
 
+pub mod answers;
+pub mod bench;
+pub mod runner;
+pub mod solution;
+pub mod visualize;
+
 use aoc_runner_derive::aoc_lib;
 
 aoc_lib! { year = 2021 }
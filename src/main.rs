@@ -1,3 +1,511 @@
-use aoc_runner_derive::aoc_main;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::process;
+use std::time::Duration;
 
-aoc_main! { lib = aoc_2021 }
+use aoc_2021::answers::{self, Answers};
+use aoc_2021::bench::{self, Benchmark, Stats};
+use aoc_2021::runner::{self, Run};
+use aoc_2021::visualize::{self, Animation};
+use aoc_2021::{FishModel, Grid, Orientations, Rasterization, Steering, SOLUTIONS};
+
+const USAGE: &str = "\
+usage:
+    aoc_2021 list [--format text|json]
+    aoc_2021 run --day N [--part N] [--impl NAME|all] [--input PATH] [--format text|json]
+    aoc_2021 verify [--answers PATH] [--inputs DIR] [--format text|json]
+    aoc_2021 bench [--day N] [--part N] [--impl NAME] [--iterations N] [--inputs DIR]
+                   [--format text|json|csv]
+    aoc_2021 stream --day 1|2 [--part N] [--window N] [--trajectory] [--input PATH]
+    aoc_2021 fish --days N [--modulus M] [--reset T] [--newborn T] [--series] [--input PATH]
+    aoc_2021 animate --day 9|11 [--steps N] [--delay MS] [--plain] [--input PATH]
+    aoc_2021 heatmap [--part N] [--image pgm|ppm] [--crop] [--output PATH]
+                     [--input PATH]
+
+Input is read from stdin when no --input is given. `--impl all` runs every
+implementation and fails if their answers differ. `verify` runs every
+implementation on DIR/dayN.txt (default input/2021) and compares the results
+with the answers file (default DIR/answers.toml). `bench` times parsing and
+solving of every matching implementation separately on DIR/dayN.txt. `stream`
+solves one part of day 1 or 2 reading the input line by line, with any sonar
+window size for day 1. `--trajectory` prints horizontal position, depth and aim
+after every day 2 command. `fish` counts day 6 lanternfish after any number of
+days, exactly or modulo M. `--reset` and `--newborn` change the spawn timers,
+and `--series` prints the population after every day. `heatmap` draws the day 5
+vent coverage that part N counts, optionally cropped to the vents, as text or
+as an image.";
+
+const FLAGS: [&str; 4] = ["--plain", "--series", "--trajectory", "--crop"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Text,
+    Json,
+    Csv,
+}
+
+/// Command line options as `--name value` pairs plus value-less flags.
+struct Options(HashMap<String, Option<String>>);
+
+impl Options {
+    fn parse(args: impl Iterator<Item = String>) -> Result<Options, Box<dyn Error>> {
+        let mut options = HashMap::new();
+        let mut args = args.peekable();
+
+        while let Some(arg) = args.next() {
+            if !arg.starts_with("--") {
+                return Err(format!("unexpected argument {}", arg).into());
+            }
+
+            let value = if FLAGS.contains(&arg.as_str()) {
+                None
+            } else {
+                Some(args.next().ok_or(format!("missing value for {}", arg))?)
+            };
+
+            options.insert(arg, value);
+        }
+
+        Ok(Options(options))
+    }
+
+    fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name).and_then(|value| value.as_deref())
+    }
+
+    fn get_parsed<T>(&self, name: &str) -> Result<Option<T>, Box<dyn Error>>
+    where
+        T: std::str::FromStr,
+        T::Err: Error + 'static,
+    {
+        self.get(name)
+            .map(|value| value.parse::<T>())
+            .transpose()
+            .map_err(|e| format!("invalid value for {}: {}", name, e).into())
+    }
+
+    /// Value of `--format`, defaulting to text. Commands list the formats
+    /// they support in `allowed`.
+    fn format(&self, allowed: &[Format]) -> Result<Format, Box<dyn Error>> {
+        let format = match self.get("--format").unwrap_or("text") {
+            "text" => Format::Text,
+            "json" => Format::Json,
+            "csv" => Format::Csv,
+            format => return Err(format!("unknown format {}", format).into()),
+        };
+
+        if !allowed.contains(&format) {
+            return Err(format!("format {:?} is not supported here", format).into());
+        }
+
+        Ok(format)
+    }
+
+    fn has(&self, name: &str) -> bool {
+        self.0.contains_key(name)
+    }
+
+    fn read_input(&self) -> io::Result<String> {
+        let input = match self.get("--input") {
+            Some(path) => fs::read_to_string(path)?,
+            None => {
+                let mut input = String::new();
+                io::stdin().read_to_string(&mut input)?;
+                input
+            }
+        };
+
+        Ok(input.trim_end_matches('\n').to_string())
+    }
+
+    fn open_input(&self) -> io::Result<Box<dyn BufRead>> {
+        Ok(match self.get("--input") {
+            Some(path) => Box::new(BufReader::new(fs::File::open(path)?)),
+            None => Box::new(io::stdin().lock()),
+        })
+    }
+}
+
+fn print_text(day: u32, part: u32, variant: Option<&str>, run: &Run) {
+    match variant {
+        Some(variant) => println!("Day {} - Part {} - {}: {}", day, part, variant, run.answer),
+        None => println!("Day {} - Part {}: {}", day, part, run.answer),
+    }
+    println!("\tgenerator: {:?},", run.parse_time);
+    println!("\trunner: {:?}", run.solve_time);
+}
+
+fn print_json(day: u32, part: u32, variant: Option<&str>, run: &Run) {
+    let output = serde_json::json!({
+        "day": day,
+        "part": part,
+        "impl": variant,
+        "answer": run.answer,
+        "parse_ns": run.parse_time.as_nanos() as u64,
+        "solve_ns": run.solve_time.as_nanos() as u64,
+    });
+    println!("{}", output);
+}
+
+fn run_command(options: &Options) -> Result<(), Box<dyn Error>> {
+    let day: u32 = options.get_parsed("--day")?.ok_or("missing --day")?;
+    let variant = options.get("--impl");
+    let parts = match options.get_parsed("--part")? {
+        Some(part) => vec![part],
+        None if variant.is_some_and(|variant| variant != "all") => {
+            return Err("--impl NAME needs --part".into())
+        }
+        None => vec![1, 2],
+    };
+    let print = match options.format(&[Format::Text, Format::Json])? {
+        Format::Json => print_json,
+        _ => print_text,
+    };
+
+    let input = options.read_input()?;
+
+    for part in parts {
+        if variant == Some("all") {
+            for (solution, run) in runner::run_variants(day, part, &input)? {
+                print(day, part, solution.variant, &run);
+            }
+        } else {
+            let run = runner::run(day, part, variant, &input)?;
+            print(day, part, variant, &run);
+        }
+    }
+
+    Ok(())
+}
+
+fn list_command(options: &Options) -> Result<(), Box<dyn Error>> {
+    let json = options.format(&[Format::Text, Format::Json])? == Format::Json;
+
+    for solution in SOLUTIONS {
+        if json {
+            let output = serde_json::json!({
+                "day": solution.day,
+                "part": solution.part,
+                "impl": solution.variant,
+            });
+            println!("{}", output);
+        } else {
+            println!("{}", solution.name());
+        }
+    }
+
+    Ok(())
+}
+
+fn verify_command(options: &Options) -> Result<(), Box<dyn Error>> {
+    let input_dir = Path::new(options.get("--inputs").unwrap_or("input/2021"));
+    let answers_path = match options.get("--answers") {
+        Some(path) => Path::new(path).to_path_buf(),
+        None => input_dir.join("answers.toml"),
+    };
+    let json = options.format(&[Format::Text, Format::Json])? == Format::Json;
+
+    let answers = Answers::parse(&fs::read_to_string(&answers_path)?)
+        .map_err(|e| format!("{}:{}", answers_path.display(), e))?;
+
+    let verifications = answers::verify(&answers, input_dir);
+
+    for verification in &verifications {
+        if json {
+            let output = serde_json::json!({
+                "day": verification.solution.day,
+                "part": verification.solution.part,
+                "impl": verification.solution.variant,
+                "status": verification.status.to_string(),
+                "pass": verification.status.is_pass(),
+            });
+            println!("{}", output);
+        } else {
+            println!("{}: {}", verification.solution.name(), verification.status);
+        }
+    }
+
+    let failures = verifications
+        .iter()
+        .filter(|verification| !verification.status.is_pass())
+        .count();
+
+    if failures > 0 {
+        return Err(format!(
+            "{} of {} solutions did not pass",
+            failures,
+            verifications.len()
+        )
+        .into());
+    }
+
+    Ok(())
+}
+
+fn stats_columns(stats: &Stats) -> [u128; 4] {
+    [stats.min, stats.median, stats.mean, stats.stddev].map(|duration| duration.as_nanos())
+}
+
+fn print_benchmark(benchmark: &Benchmark, format: Format) {
+    let solution = benchmark.solution;
+
+    match format {
+        Format::Text => {
+            println!("{} ({} iterations)", solution.name(), benchmark.iterations);
+            for (phase, stats) in [
+                ("generator", &benchmark.parse),
+                ("runner", &benchmark.solve),
+            ] {
+                println!(
+                    "\t{}: min {:?}, median {:?}, mean {:?}, stddev {:?}",
+                    phase, stats.min, stats.median, stats.mean, stats.stddev
+                );
+            }
+        }
+        Format::Json => {
+            let [parse_min, parse_median, parse_mean, parse_stddev] =
+                stats_columns(&benchmark.parse);
+            let [solve_min, solve_median, solve_mean, solve_stddev] =
+                stats_columns(&benchmark.solve);
+            let output = serde_json::json!({
+                "day": solution.day,
+                "part": solution.part,
+                "impl": solution.variant,
+                "iterations": benchmark.iterations,
+                "parse_ns": {
+                    "min": parse_min as u64,
+                    "median": parse_median as u64,
+                    "mean": parse_mean as u64,
+                    "stddev": parse_stddev as u64,
+                },
+                "solve_ns": {
+                    "min": solve_min as u64,
+                    "median": solve_median as u64,
+                    "mean": solve_mean as u64,
+                    "stddev": solve_stddev as u64,
+                },
+            });
+            println!("{}", output);
+        }
+        Format::Csv => {
+            let columns = stats_columns(&benchmark.parse)
+                .into_iter()
+                .chain(stats_columns(&benchmark.solve))
+                .map(|nanos| nanos.to_string())
+                .collect::<Vec<_>>();
+            println!(
+                "{},{},{},{},{}",
+                solution.day,
+                solution.part,
+                solution.variant.unwrap_or(""),
+                benchmark.iterations,
+                columns.join(",")
+            );
+        }
+    }
+}
+
+fn bench_command(options: &Options) -> Result<(), Box<dyn Error>> {
+    let day: Option<u32> = options.get_parsed("--day")?;
+    let part: Option<u32> = options.get_parsed("--part")?;
+    let variant = options.get("--impl");
+    let iterations: usize = options.get_parsed("--iterations")?.unwrap_or(10);
+    let input_dir = Path::new(options.get("--inputs").unwrap_or("input/2021"));
+    let format = options.format(&[Format::Text, Format::Json, Format::Csv])?;
+
+    let solutions = SOLUTIONS.iter().filter(|solution| {
+        day.is_none_or(|day| solution.day == day)
+            && part.is_none_or(|part| solution.part == part)
+            && variant.is_none_or(|variant| solution.variant == Some(variant))
+    });
+
+    if format == Format::Csv {
+        println!(
+            "day,part,impl,iterations,\
+             parse_min_ns,parse_median_ns,parse_mean_ns,parse_stddev_ns,\
+             solve_min_ns,solve_median_ns,solve_mean_ns,solve_stddev_ns"
+        );
+    }
+
+    for solution in solutions {
+        let input_path = input_dir.join(format!("day{}.txt", solution.day));
+        let input = fs::read_to_string(&input_path)
+            .map_err(|e| format!("{}: {}", input_path.display(), e))?;
+
+        let benchmark = bench::bench_solution(solution, input.trim_end_matches('\n'), iterations)
+            .map_err(|e| format!("{}: {}", input_path.display(), e))?;
+        print_benchmark(&benchmark, format);
+    }
+
+    Ok(())
+}
+
+fn print_trajectory(reader: impl BufRead, steering: Steering) -> Result<(), Box<dyn Error>> {
+    let mut error = None;
+    let directions = aoc_2021::read_directions(reader)
+        .map_while(|direction| direction.map_err(|e| error = Some(e)).ok());
+
+    println!("horizontal\tdepth\taim");
+    for position in aoc_2021::trajectory(directions, steering) {
+        let position = position?;
+        println!(
+            "{}\t{}\t{}",
+            position.horizontal, position.depth, position.aim
+        );
+    }
+
+    match error {
+        Some(error) => Err(error.into()),
+        None => Ok(()),
+    }
+}
+
+fn stream_command(options: &Options) -> Result<(), Box<dyn Error>> {
+    let day: u32 = options.get_parsed("--day")?.ok_or("missing --day")?;
+    let part: u32 = options.get_parsed("--part")?.unwrap_or(1);
+    let window: Option<usize> = options.get_parsed("--window")?;
+
+    let answer = match (day, part) {
+        (1, 1 | 2) => {
+            let window = window.unwrap_or(if part == 1 { 1 } else { 3 });
+            if window == 0 {
+                return Err("window must be positive".into());
+            }
+            aoc_2021::count_increases_streaming(options.open_input()?, window)?.to_string()
+        }
+        (2, 1 | 2) => {
+            let steering = if part == 1 {
+                Steering::Depth
+            } else {
+                Steering::Aim
+            };
+            if options.has("--trajectory") {
+                return print_trajectory(options.open_input()?, steering);
+            }
+            aoc_2021::follow_streaming(options.open_input()?, steering)?
+                .product()?
+                .to_string()
+        }
+        _ => return Err(format!("no streaming solution for day {} part {}", day, part).into()),
+    };
+
+    println!("{}", answer);
+
+    Ok(())
+}
+
+fn fish_command(options: &Options) -> Result<(), Box<dyn Error>> {
+    let days: u64 = options.get_parsed("--days")?.ok_or("missing --days")?;
+    let modulus: Option<u64> = options.get_parsed("--modulus")?;
+    let model = FishModel {
+        reset_timer: options.get_parsed("--reset")?.unwrap_or(6),
+        newborn_timer: options.get_parsed("--newborn")?.unwrap_or(8),
+    };
+    let series = options.has("--series");
+
+    let fishes = aoc_2021::parse_lanternfish(&options.read_input()?)?;
+
+    if series || model != FishModel::LANTERNFISH {
+        if modulus.is_some() {
+            return Err("--modulus only supports the default model without --series".into());
+        }
+
+        let days = usize::try_from(days).map_err(|_| "--days is too large for a series")?;
+        let populations = aoc_2021::population_series(&fishes, days, &model)?;
+        if series {
+            for (day, population) in populations.iter().enumerate() {
+                println!("{}\t{}", day, population);
+            }
+        } else {
+            println!("{}", populations.last().unwrap());
+        }
+
+        return Ok(());
+    }
+
+    match modulus {
+        Some(0) => return Err("modulus must be positive".into()),
+        Some(modulus) => println!("{}", aoc_2021::count_fish_modular(&fishes, days, modulus)?),
+        None => println!("{}", aoc_2021::count_fish_matrix(&fishes, days)?),
+    }
+
+    Ok(())
+}
+
+fn animate_command(options: &Options) -> Result<(), Box<dyn Error>> {
+    let day: u32 = options.get_parsed("--day")?.ok_or("missing --day")?;
+    let steps: usize = options.get_parsed("--steps")?.unwrap_or(100);
+    let delay = Duration::from_millis(options.get_parsed("--delay")?.unwrap_or(100));
+    let animation = if options.has("--plain") {
+        Animation::plain()
+    } else {
+        Animation::ansi(delay)
+    };
+
+    let grid = Grid::parse_digits(&options.read_input()?)?;
+
+    let mut stdout = io::stdout();
+    match day {
+        9 => animation.play(visualize::basin_frames(&grid), &mut stdout)?,
+        11 => animation.play(visualize::octopus_frames(grid, steps), &mut stdout)?,
+        _ => return Err(format!("no animation for day {}", day).into()),
+    }
+
+    Ok(())
+}
+
+fn heatmap_command(options: &Options) -> Result<(), Box<dyn Error>> {
+    let orientations = match options.get_parsed("--part")?.unwrap_or(2) {
+        1 => Orientations::AXES,
+        2 => Orientations::ALL,
+        part => return Err(format!("no part {}", part).into()),
+    };
+    let image = options.get("--image");
+    if let Some(image) = image.filter(|&image| image != "pgm" && image != "ppm") {
+        return Err(format!("unknown image format {}", image).into());
+    }
+
+    let lines = aoc_2021::parse_vents(&options.read_input()?)?;
+    let mut coverage = aoc_2021::coverage_grid(&lines, orientations, Rasterization::Strict)?;
+    if options.has("--crop") {
+        coverage = visualize::crop_to_vents(&coverage);
+    }
+
+    let mut out: Box<dyn Write> = match options.get("--output") {
+        Some(path) => Box::new(BufWriter::new(fs::File::create(path)?)),
+        None => Box::new(io::stdout().lock()),
+    };
+    match image {
+        Some("pgm") => visualize::write_pgm(&coverage, &mut out)?,
+        Some("ppm") => visualize::write_ppm(&coverage, &mut out)?,
+        _ => out.write_all(visualize::heatmap_ascii(&coverage).as_bytes())?,
+    }
+    out.flush()?;
+
+    Ok(())
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let command = args.next();
+
+    let result = Options::parse(args).and_then(|options| match command.as_deref() {
+        Some("list") => list_command(&options),
+        Some("run") => run_command(&options),
+        Some("verify") => verify_command(&options),
+        Some("bench") => bench_command(&options),
+        Some("stream") => stream_command(&options),
+        Some("fish") => fish_command(&options),
+        Some("animate") => animate_command(&options),
+        Some("heatmap") => heatmap_command(&options),
+        _ => Err(USAGE.into()),
+    });
+
+    if let Err(error) = result {
+        eprintln!("{}", error);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,191 @@
+use std::fmt::{self, Display};
+use std::time::{Duration, Instant};
+
+use crate::solution::{self, Solution, SolveError};
+use crate::util::parse::ParseError;
+
+/// Answer of one solution run along with how long each phase took.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Run {
+    pub answer: String,
+    pub parse_time: Duration,
+    pub solve_time: Duration,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RunError {
+    Parse(ParseError),
+    Solve(SolveError),
+    UnknownSolution {
+        day: u32,
+        part: u32,
+        variant: Option<String>,
+    },
+    /// Variants of the same part produced different answers.
+    Disagreement {
+        day: u32,
+        part: u32,
+        answers: Vec<(String, String)>,
+    },
+}
+
+impl Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RunError::Parse(error) => write!(f, "invalid input: {}", error),
+            RunError::Solve(error) => write!(f, "no answer: {}", error),
+            RunError::UnknownSolution { day, part, variant } => {
+                write!(f, "no solution for day {} part {}", day, part)?;
+                if let Some(variant) = variant {
+                    write!(f, " ({})", variant)?;
+                }
+                Ok(())
+            }
+            RunError::Disagreement { day, part, answers } => {
+                write!(f, "day {} part {} variants disagree:", day, part)?;
+                for (name, answer) in answers {
+                    write!(f, " {}={}", name, answer)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for RunError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RunError::Parse(error) => Some(error),
+            RunError::Solve(error) => Some(error),
+            RunError::UnknownSolution { .. } | RunError::Disagreement { .. } => None,
+        }
+    }
+}
+
+impl From<ParseError> for RunError {
+    fn from(error: ParseError) -> RunError {
+        RunError::Parse(error)
+    }
+}
+
+impl From<SolveError> for RunError {
+    fn from(error: SolveError) -> RunError {
+        RunError::Solve(error)
+    }
+}
+
+/// Runs the generator and solver of `solution` on `input`, timing both.
+pub fn run_solution(solution: &Solution, input: &str) -> Result<Run, RunError> {
+    let start = Instant::now();
+    let parsed = (solution.generator)(input)?;
+    let parse_time = start.elapsed();
+
+    let start = Instant::now();
+    let answer = (solution.solver)(parsed.as_ref())?;
+    let solve_time = start.elapsed();
+
+    Ok(Run {
+        answer,
+        parse_time,
+        solve_time,
+    })
+}
+
+/// Runs a single solution on `input`. Days with several implementations of a
+/// part select one with `variant`; `None` picks the default.
+pub fn run(day: u32, part: u32, variant: Option<&str>, input: &str) -> Result<Run, RunError> {
+    let solution = solution::find(day, part, variant).ok_or_else(|| RunError::UnknownSolution {
+        day,
+        part,
+        variant: variant.map(str::to_string),
+    })?;
+
+    run_solution(solution, input)
+}
+
+/// Runs every variant of a part on `input` and checks that they all give the
+/// same answer.
+pub fn run_variants(
+    day: u32,
+    part: u32,
+    input: &str,
+) -> Result<Vec<(&'static Solution, Run)>, RunError> {
+    let mut runs = vec![];
+
+    for solution in solution::variants(day, part) {
+        runs.push((solution, run_solution(solution, input)?));
+    }
+
+    if runs.is_empty() {
+        return Err(RunError::UnknownSolution {
+            day,
+            part,
+            variant: None,
+        });
+    }
+
+    if runs.iter().any(|(_, run)| run.answer != runs[0].1.answer) {
+        return Err(RunError::Disagreement {
+            day,
+            part,
+            answers: runs
+                .iter()
+                .map(|(solution, run)| (solution.name(), run.answer.clone()))
+                .collect(),
+        });
+    }
+
+    Ok(runs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn run_by_variant() {
+        let input =
+            "acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab | cdfeb fcadb cdfeb cdbaf";
+
+        assert_eq!(run(8, 2, None, input).unwrap().answer, "5353");
+        assert_eq!(
+            run(8, 2, Some("permutation"), input).unwrap().answer,
+            "5353"
+        );
+        assert_eq!(
+            run(8, 2, Some("magic"), input),
+            Err(RunError::UnknownSolution {
+                day: 8,
+                part: 2,
+                variant: Some("magic".to_string())
+            })
+        );
+    }
+
+    #[test]
+    fn run_all_variants() {
+        let input =
+            "acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab | cdfeb fcadb cdfeb cdbaf";
+        let runs = run_variants(8, 2, input).unwrap();
+
+        assert_eq!(runs.len(), 2);
+        assert!(runs.iter().all(|(_, run)| run.answer == "5353"));
+    }
+
+    #[test]
+    fn run_parse_error() {
+        assert!(matches!(
+            run(1, 1, None, "1\nx"),
+            Err(RunError::Parse(ParseError::Syntax { line: 2, .. }))
+        ));
+    }
+
+    #[test]
+    fn run_solve_error() {
+        assert!(matches!(
+            run(6, 1, Some("matrix"), "3,9"),
+            Err(RunError::Solve(_))
+        ));
+        assert!(matches!(run(3, 2, None, "10\n11"), Err(RunError::Solve(_))));
+    }
+}
@@ -0,0 +1,243 @@
+use std::any::Any;
+use std::fmt::{self, Display};
+
+use num::BigUint;
+
+use crate::util::parse::ParseError;
+use crate::{day01, day02, day03, day04, day05, day06, day07, day08, day09, day10, day11};
+
+pub type Generator = fn(&str) -> Result<Box<dyn Any>, ParseError>;
+pub type Solver = fn(&dyn Any) -> Result<String, SolveError>;
+
+/// A solver rejected input that its generator accepted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SolveError(pub String);
+
+impl Display for SolveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for SolveError {}
+
+/// Solver return values: plain answers, or a `Result` for solvers that can
+/// fail.
+pub trait Answer {
+    fn into_answer(self) -> Result<String, SolveError>;
+}
+
+macro_rules! impl_answer {
+    ($($answer:ty),*) => {
+        $(
+            impl Answer for $answer {
+                fn into_answer(self) -> Result<String, SolveError> {
+                    Ok(self.to_string())
+                }
+            }
+        )*
+    };
+}
+
+impl_answer!(u32, u64, u128, usize, i32, i64, isize, BigUint);
+
+impl<T: Answer, E: Display> Answer for Result<T, E> {
+    fn into_answer(self) -> Result<String, SolveError> {
+        self.map_err(|error| SolveError(error.to_string()))?
+            .into_answer()
+    }
+}
+
+/// A registered implementation of one puzzle part. The generator output is
+/// type-erased so that every day fits in one table; `solver` must only be
+/// given the output of the `generator` of the same entry.
+#[derive(Clone, Copy)]
+pub struct Solution {
+    pub day: u32,
+    pub part: u32,
+    /// Name of an alternative implementation, `None` for the only one.
+    pub variant: Option<&'static str>,
+    pub generator: Generator,
+    pub solver: Solver,
+}
+
+impl Solution {
+    pub fn name(&self) -> String {
+        match self.variant {
+            Some(variant) => format!("day{}/part{}/{}", self.day, self.part, variant),
+            None => format!("day{}/part{}", self.day, self.part),
+        }
+    }
+}
+
+impl fmt::Debug for Solution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Solution")
+            .field("day", &self.day)
+            .field("part", &self.part)
+            .field("variant", &self.variant)
+            .finish()
+    }
+}
+
+/// Recovers the concrete generator output. The generator argument only pins
+/// down the type.
+fn downcast<T, G>(parsed: &dyn Any, _generator: G) -> &T
+where
+    T: 'static,
+    G: Fn(&str) -> Result<T, ParseError>,
+{
+    parsed
+        .downcast_ref::<T>()
+        .expect("solver called with the output of another generator")
+}
+
+macro_rules! solution {
+    ($day:expr, $part:expr, $variant:expr, $generator:path, $solver:path) => {
+        Solution {
+            day: $day,
+            part: $part,
+            variant: $variant,
+            generator: |input| Ok(Box::new($generator(input)?)),
+            solver: |parsed| {
+                let parsed = downcast(parsed, $generator);
+                $solver(parsed).into_answer()
+            },
+        }
+    };
+}
+
+/// Every solution in the crate, ordered by day and part. The first entry of a
+/// day and part is its default implementation.
+pub const SOLUTIONS: &[Solution] = &[
+    solution!(1, 1, None, day01::parse_input, day01::part1),
+    solution!(1, 2, None, day01::parse_input, day01::part2),
+    solution!(2, 1, None, day02::parse_input, day02::part1),
+    solution!(2, 2, None, day02::parse_input, day02::part2),
+    solution!(3, 1, None, day03::parse_input, day03::part1),
+    solution!(3, 2, None, day03::parse_input, day03::part2),
+    solution!(4, 1, Some("scan"), day04::parse_input, day04::part1),
+    solution!(
+        4,
+        1,
+        Some("indexed"),
+        day04::parse_input,
+        day04::part1_indexed
+    ),
+    solution!(4, 2, Some("scan"), day04::parse_input, day04::part2),
+    solution!(
+        4,
+        2,
+        Some("indexed"),
+        day04::parse_input,
+        day04::part2_indexed
+    ),
+    solution!(5, 1, Some("dense"), day05::parse_input, day05::part1),
+    solution!(
+        5,
+        1,
+        Some("sparse"),
+        day05::parse_input,
+        day05::part1_sparse
+    ),
+    solution!(
+        5,
+        1,
+        Some("analytic"),
+        day05::parse_input,
+        day05::part1_analytic
+    ),
+    solution!(5, 2, Some("dense"), day05::parse_input, day05::part2),
+    solution!(
+        5,
+        2,
+        Some("sparse"),
+        day05::parse_input,
+        day05::part2_sparse
+    ),
+    solution!(
+        5,
+        2,
+        Some("analytic"),
+        day05::parse_input,
+        day05::part2_analytic
+    ),
+    solution!(6, 1, Some("rotate"), day06::parse_input, day06::part1),
+    solution!(
+        6,
+        1,
+        Some("matrix"),
+        day06::parse_input,
+        day06::part1_matrix
+    ),
+    solution!(6, 2, Some("rotate"), day06::parse_input, day06::part2),
+    solution!(
+        6,
+        2,
+        Some("matrix"),
+        day06::parse_input,
+        day06::part2_matrix
+    ),
+    solution!(7, 1, Some("brute_force"), day07::parse_input, day07::part1),
+    solution!(
+        7,
+        1,
+        Some("median"),
+        day07::parse_input,
+        day07::part1_median
+    ),
+    solution!(7, 2, Some("brute_force"), day07::parse_input, day07::part2),
+    solution!(7, 2, Some("mean"), day07::parse_input, day07::part2_mean),
+    solution!(8, 1, None, day08::parse_input, day08::part1),
+    solution!(8, 2, Some("naive"), day08::parse_input, day08::part2),
+    solution!(
+        8,
+        2,
+        Some("permutation"),
+        day08::parse_input,
+        day08::part2_permutation
+    ),
+    solution!(9, 1, None, day09::parse_input, day09::part1),
+    solution!(9, 2, None, day09::parse_input, day09::part2),
+    solution!(10, 1, None, day10::parse_input, day10::part1),
+    solution!(10, 2, None, day10::parse_input, day10::part2),
+    solution!(11, 1, None, day11::parse_input, day11::part1),
+    solution!(11, 2, None, day11::parse_input, day11::part2),
+];
+
+/// All implementations of a day and part, default first.
+pub fn variants(day: u32, part: u32) -> impl Iterator<Item = &'static Solution> {
+    SOLUTIONS
+        .iter()
+        .filter(move |solution| solution.day == day && solution.part == part)
+}
+
+/// Looks up a solution by name, or the default one when `variant` is `None`.
+pub fn find(day: u32, part: u32, variant: Option<&str>) -> Option<&'static Solution> {
+    variants(day, part).find(|solution| variant.is_none() || solution.variant == variant)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_variants() {
+        assert_eq!(find(8, 2, None).unwrap().variant, Some("naive"));
+        assert_eq!(
+            find(8, 2, Some("permutation")).unwrap().name(),
+            "day8/part2/permutation"
+        );
+        assert!(find(8, 2, Some("magic")).is_none());
+        assert!(find(1, 1, Some("naive")).is_none());
+        assert_eq!(variants(8, 2).count(), 2);
+    }
+
+    #[test]
+    fn generator_and_solver() {
+        let solution = find(1, 1, None).unwrap();
+        let parsed = (solution.generator)("199\n200\n208\n210").unwrap();
+
+        assert_eq!((solution.solver)(parsed.as_ref()), Ok("3".to_string()));
+    }
+}
@@ -0,0 +1,227 @@
+use std::cmp;
+use std::collections::{BTreeMap, BTreeSet};
+
+pub type Point = (i64, i64);
+
+fn cross((ax, ay): Point, (bx, by): Point) -> i64 {
+    ax * by - ay * bx
+}
+
+/// Horizontal, vertical or 45° diagonal segment covering the lattice points
+/// from `start` to `end`, both included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Segment {
+    start: Point,
+    /// Unit step from one covered point to the next.
+    direction: Point,
+    /// Number of steps from `start` to the end.
+    length: i64,
+}
+
+impl Segment {
+    /// `None` unless the segment is horizontal, vertical or 45° diagonal.
+    pub fn new(start: Point, end: Point) -> Option<Segment> {
+        let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+
+        if dx != 0 && dy != 0 && dx.abs() != dy.abs() {
+            return None;
+        }
+
+        Some(Segment {
+            start,
+            direction: (dx.signum(), dy.signum()),
+            length: cmp::max(dx.abs(), dy.abs()),
+        })
+    }
+
+    pub fn start(&self) -> Point {
+        self.start
+    }
+
+    pub fn end(&self) -> Point {
+        self.at(self.length)
+    }
+
+    pub fn is_diagonal(&self) -> bool {
+        self.direction.0 != 0 && self.direction.1 != 0
+    }
+
+    fn at(&self, step: i64) -> Point {
+        (
+            self.start.0 + step * self.direction.0,
+            self.start.1 + step * self.direction.1,
+        )
+    }
+
+    /// Step at which the segment's infinite line passes through `point`, if
+    /// it does at a whole step.
+    fn step_to(&self, point: Point) -> Option<i64> {
+        let offset = (point.0 - self.start.0, point.1 - self.start.1);
+        if cross(offset, self.direction) != 0 {
+            return None;
+        }
+
+        Some(if self.direction.0 != 0 {
+            offset.0 * self.direction.0
+        } else {
+            offset.1 * self.direction.1
+        })
+    }
+
+    pub fn contains(&self, point: Point) -> bool {
+        if self.length == 0 {
+            return point == self.start;
+        }
+
+        self.step_to(point)
+            .is_some_and(|step| (0..=self.length).contains(&step))
+    }
+
+    pub fn points(&self) -> impl Iterator<Item = Point> + '_ {
+        (0..=self.length).map(|step| self.at(step))
+    }
+
+    /// Lattice points covered by both segments. Collinear segments share a
+    /// run of points; crossing segments share at most one, and none when
+    /// diagonals cross between lattice points.
+    pub fn intersection(&self, other: &Segment) -> Vec<Point> {
+        // Single points have no direction to compare.
+        for (point, segment) in [(self, other), (other, self)] {
+            if point.length == 0 {
+                return if segment.contains(point.start) {
+                    vec![point.start]
+                } else {
+                    vec![]
+                };
+            }
+        }
+
+        let denominator = cross(self.direction, other.direction);
+
+        if denominator == 0 {
+            let (Some(a), Some(b)) = (self.step_to(other.start), self.step_to(other.end())) else {
+                return vec![];
+            };
+            let first = cmp::max(0, cmp::min(a, b));
+            let last = cmp::min(self.length, cmp::max(a, b));
+
+            return (first..=last).map(|step| self.at(step)).collect();
+        }
+
+        let offset = (other.start.0 - self.start.0, other.start.1 - self.start.1);
+        let self_step = cross(offset, other.direction);
+        let other_step = cross(offset, self.direction);
+
+        if self_step % denominator != 0 || other_step % denominator != 0 {
+            return vec![];
+        }
+
+        let (self_step, other_step) = (self_step / denominator, other_step / denominator);
+        if (0..=self.length).contains(&self_step) && (0..=other.length).contains(&other_step) {
+            vec![self.at(self_step)]
+        } else {
+            vec![]
+        }
+    }
+}
+
+/// Every point covered by two or more segments, with the indices of all the
+/// segments covering it. Compares segments pairwise, so the work depends on
+/// the number of segments and overlaps rather than on the area they span.
+pub fn overlaps(segments: &[Segment]) -> BTreeMap<Point, BTreeSet<usize>> {
+    let mut overlaps: BTreeMap<Point, BTreeSet<usize>> = BTreeMap::new();
+
+    for (i, a) in segments.iter().enumerate() {
+        for (j, b) in segments.iter().enumerate().skip(i + 1) {
+            for point in a.intersection(b) {
+                overlaps.entry(point).or_default().extend([i, j]);
+            }
+        }
+    }
+
+    overlaps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(start: Point, end: Point) -> Segment {
+        Segment::new(start, end).unwrap()
+    }
+
+    #[test]
+    fn new_segment() {
+        assert_eq!(Segment::new((0, 0), (2, 1)), None);
+        assert_eq!(segment((3, 3), (0, 0)).end(), (0, 0));
+        assert!(segment((3, 0), (0, 3)).is_diagonal());
+        assert_eq!(
+            segment((0, 2), (2, 0)).points().collect::<Vec<_>>(),
+            vec![(0, 2), (1, 1), (2, 0)]
+        );
+        assert_eq!(segment((5, 5), (5, 5)).points().count(), 1);
+        assert!(segment((4, 0), (0, 4)).contains((1, 3)));
+        assert!(!segment((4, 0), (0, 4)).contains((5, -1)));
+    }
+
+    #[test]
+    fn intersections() {
+        // Crossing
+        assert_eq!(
+            segment((0, 2), (4, 2)).intersection(&segment((1, 0), (1, 5))),
+            vec![(1, 2)]
+        );
+        assert_eq!(
+            segment((0, 0), (4, 4)).intersection(&segment((0, 4), (4, 0))),
+            vec![(2, 2)]
+        );
+        // Diagonals crossing between lattice points
+        assert!(segment((0, 0), (1, 1))
+            .intersection(&segment((0, 1), (1, 0)))
+            .is_empty());
+        // Collinear, in opposite directions
+        assert_eq!(
+            segment((0, 0), (5, 5)).intersection(&segment((7, 7), (3, 3))),
+            vec![(3, 3), (4, 4), (5, 5)]
+        );
+        assert_eq!(
+            segment((9, 4), (3, 4)).intersection(&segment((3, 4), (1, 4))),
+            vec![(3, 4)]
+        );
+        // Parallel but apart, and collinear but disjoint
+        assert!(segment((0, 0), (3, 0))
+            .intersection(&segment((0, 1), (3, 1)))
+            .is_empty());
+        assert!(segment((0, 0), (3, 0))
+            .intersection(&segment((5, 0), (7, 0)))
+            .is_empty());
+        // Single points
+        assert_eq!(
+            segment((2, 2), (2, 2)).intersection(&segment((0, 4), (4, 0))),
+            vec![(2, 2)]
+        );
+        assert!(segment((1, 0), (3, 0))
+            .intersection(&segment((4, 0), (4, 0)))
+            .is_empty());
+        // Crossing lines, segments too short
+        assert!(segment((0, 0), (1, 0))
+            .intersection(&segment((3, -1), (3, 1)))
+            .is_empty());
+    }
+
+    #[test]
+    fn overlap_multiplicities() {
+        let segments = [
+            segment((0, 0), (4, 0)),
+            segment((2, 0), (2, 3)),
+            segment((0, 2), (2, 0)),
+            segment((3, 0), (6, 0)),
+        ];
+
+        let overlaps = overlaps(&segments);
+        assert_eq!(overlaps.len(), 3);
+        assert_eq!(overlaps[&(2, 0)], BTreeSet::from([0, 1, 2]));
+        assert_eq!(overlaps[&(3, 0)], BTreeSet::from([0, 3]));
+        assert_eq!(overlaps[&(4, 0)], BTreeSet::from([0, 3]));
+    }
+}
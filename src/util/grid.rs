@@ -0,0 +1,346 @@
+use pest::Parser;
+use std::ops::{Index, IndexMut, Range};
+
+use crate::util;
+use crate::util::parse::ParseError;
+
+#[derive(Parser)]
+#[grammar_inline = r#"
+    cell = { ASCII_DIGIT }
+    row = { cell+ }
+    input = _{ SOI ~ NEWLINE? ~ ((" "+)? ~ row ~ NEWLINE?)+ ~ " "* ~ EOI }
+"#]
+struct DigitParser;
+
+const NEIGHBORS_4: [(isize, isize); 4] = [(0, -1), (0, 1), (-1, 0), (1, 0)];
+
+#[rustfmt::skip]
+const NEIGHBORS_8: [(isize, isize); 8] = [
+    (-1, -1), (0, -1), (1, -1),
+    (-1,  0),          (1,  0),
+    (-1,  1), (0,  1), (1,  1),
+];
+
+/// Rectangular grid stored in row-major order and addressed by `(x, y)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grid<T> {
+    width: usize,
+    height: usize,
+    cells: Vec<T>,
+}
+
+impl<T> Grid<T> {
+    pub fn new(width: usize, height: usize, value: T) -> Grid<T>
+    where
+        T: Clone,
+    {
+        Grid {
+            width,
+            height,
+            cells: vec![value; width * height],
+        }
+    }
+
+    /// Builds a grid from row-major cells. Panics if `cells` does not fill
+    /// whole rows of `width`.
+    pub fn from_vec(width: usize, cells: Vec<T>) -> Grid<T> {
+        let height = cells.len().checked_div(width).unwrap_or(0);
+        assert_eq!(width * height, cells.len(), "cells do not fill the grid");
+
+        Grid {
+            width,
+            height,
+            cells,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        x < self.width && y < self.height
+    }
+
+    pub fn index_to_coord(&self, index: usize) -> (usize, usize) {
+        (index % self.width, index / self.width)
+    }
+
+    pub fn coord_to_index(&self, x: usize, y: usize) -> usize {
+        y * self.width + x
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<&T> {
+        if self.contains(x, y) {
+            Some(&self.cells[self.coord_to_index(x, y)])
+        } else {
+            None
+        }
+    }
+
+    pub fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut T> {
+        if self.contains(x, y) {
+            let index = self.coord_to_index(x, y);
+            Some(&mut self.cells[index])
+        } else {
+            None
+        }
+    }
+
+    /// Stores `value` at `(x, y)`, returning the previous value or `None` if
+    /// the coordinate is outside the grid.
+    pub fn set(&mut self, x: usize, y: usize, value: T) -> Option<T> {
+        self.get_mut(x, y)
+            .map(|cell| std::mem::replace(cell, value))
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        self.cells.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, T> {
+        self.cells.iter_mut()
+    }
+
+    /// All coordinates in row-major order.
+    pub fn coordinates(&self) -> impl Iterator<Item = (usize, usize)> {
+        let width = self.width;
+        (0..self.height).flat_map(move |y| (0..width).map(move |x| (x, y)))
+    }
+
+    pub fn row(&self, y: usize) -> &[T] {
+        &self.cells[y * self.width..(y + 1) * self.width]
+    }
+
+    pub fn rows(&self) -> std::slice::Chunks<'_, T> {
+        self.cells.chunks(self.width.max(1))
+    }
+
+    /// Cells of column `x` from top to bottom. Panics if `x` is outside the
+    /// grid.
+    pub fn column(&self, x: usize) -> impl Iterator<Item = &T> {
+        assert!(x < self.width, "column {} is outside the grid", x);
+        self.cells.iter().skip(x).step_by(self.width.max(1))
+    }
+
+    pub fn columns(&self) -> impl Iterator<Item = impl Iterator<Item = &T>> {
+        (0..self.width).map(move |x| self.column(x))
+    }
+
+    /// Coordinates directly above, below, left and right of `(x, y)` that lie
+    /// inside the grid.
+    pub fn neighbors4(&self, x: usize, y: usize) -> impl Iterator<Item = (usize, usize)> {
+        self.neighbors(x, y, &NEIGHBORS_4)
+    }
+
+    /// Coordinates of all eight surrounding cells of `(x, y)` that lie inside
+    /// the grid.
+    pub fn neighbors8(&self, x: usize, y: usize) -> impl Iterator<Item = (usize, usize)> {
+        self.neighbors(x, y, &NEIGHBORS_8)
+    }
+
+    fn neighbors(
+        &self,
+        x: usize,
+        y: usize,
+        offsets: &'static [(isize, isize)],
+    ) -> impl Iterator<Item = (usize, usize)> {
+        let (width, height) = (self.width, self.height);
+
+        offsets.iter().filter_map(move |&(dx, dy)| {
+            let nx = x.checked_add_signed(dx)?;
+            let ny = y.checked_add_signed(dy)?;
+            if nx < width && ny < height {
+                Some((nx, ny))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Copy of the cells in `columns` and `rows`. Panics if the ranges reach
+    /// outside the grid.
+    pub fn crop(&self, columns: Range<usize>, rows: Range<usize>) -> Grid<T>
+    where
+        T: Clone,
+    {
+        assert!(columns.end <= self.width && rows.end <= self.height);
+
+        let cells = rows
+            .flat_map(|y| self.row(y)[columns.clone()].iter().cloned())
+            .collect();
+        Grid::from_vec(columns.len(), cells)
+    }
+
+    /// Smallest ranges of columns and rows holding every cell that matches
+    /// `predicate`, or `None` if no cell does.
+    pub fn bounds<F>(&self, predicate: F) -> Option<(Range<usize>, Range<usize>)>
+    where
+        F: Fn(&T) -> bool,
+    {
+        let mut matching = self.coordinates().filter(|&coord| predicate(&self[coord]));
+        let (x, y) = matching.next()?;
+
+        let (columns, rows) = matching.fold((x..x + 1, y..y + 1), |(columns, rows), (x, y)| {
+            (
+                columns.start.min(x)..columns.end.max(x + 1),
+                rows.start.min(y)..rows.end.max(y + 1),
+            )
+        });
+        Some((columns, rows))
+    }
+
+    pub fn map<U, F>(&self, f: F) -> Grid<U>
+    where
+        F: FnMut(&T) -> U,
+    {
+        Grid {
+            width: self.width,
+            height: self.height,
+            cells: self.cells.iter().map(f).collect(),
+        }
+    }
+}
+
+impl Grid<u8> {
+    /// Parses lines of single digits into a grid. Leading spaces on a line and
+    /// a leading blank line are ignored; all rows must be equally long.
+    pub fn parse_digits(input: &str) -> Result<Grid<u8>, ParseError> {
+        let input_tokens = DigitParser::parse(Rule::input, input)?;
+
+        let mut width: Option<usize> = None;
+        let mut cells = vec![];
+
+        for token in input_tokens {
+            match token.as_rule() {
+                Rule::row => (),
+                Rule::EOI => continue,
+                _ => return Err(ParseError::unexpected_rule(&token)),
+            }
+
+            let (line, _column) = token.line_col();
+            let row_start = cells.len();
+
+            for cell_token in token.into_inner() {
+                cells.push(util::parse::token_to_number(cell_token, Rule::cell)?);
+            }
+
+            let row_width = cells.len() - row_start;
+            match width {
+                None => width = Some(row_width),
+                Some(expected) if expected != row_width => {
+                    return Err(ParseError::GridShape {
+                        line,
+                        expected,
+                        found: row_width,
+                    })
+                }
+                Some(_) => (),
+            }
+        }
+
+        Ok(Grid::from_vec(width.unwrap_or(0), cells))
+    }
+}
+
+impl<T> Index<(usize, usize)> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, (x, y): (usize, usize)) -> &T {
+        assert!(self.contains(x, y), "({}, {}) is outside the grid", x, y);
+        &self.cells[self.coord_to_index(x, y)]
+    }
+}
+
+impl<T> IndexMut<(usize, usize)> for Grid<T> {
+    fn index_mut(&mut self, (x, y): (usize, usize)) -> &mut T {
+        assert!(self.contains(x, y), "({}, {}) is outside the grid", x, y);
+        let index = self.coord_to_index(x, y);
+        &mut self.cells[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_digits() {
+        let grid = Grid::parse_digits("123\n456").unwrap();
+
+        assert_eq!(grid.width(), 3);
+        assert_eq!(grid.height(), 2);
+        assert_eq!(grid[(2, 1)], 6);
+        assert_eq!(grid.get(3, 0), None);
+        assert_eq!(grid.rows().collect::<Vec<_>>(), vec![[1, 2, 3], [4, 5, 6]]);
+        assert_eq!(grid.column(1).collect::<Vec<_>>(), vec![&2, &5]);
+    }
+
+    #[test]
+    #[should_panic(expected = "column 3 is outside the grid")]
+    fn column_outside() {
+        let _ = Grid::parse_digits("123\n456").unwrap().column(3);
+    }
+
+    #[test]
+    fn parse_digits_error() {
+        assert_eq!(
+            Grid::parse_digits("123\n45\n678"),
+            Err(ParseError::GridShape {
+                line: 2,
+                expected: 3,
+                found: 2
+            })
+        );
+    }
+
+    #[test]
+    fn set() {
+        let mut grid = Grid::new(2, 2, 0);
+
+        assert_eq!(grid.set(1, 1, 5), Some(0));
+        assert_eq!(grid.set(2, 1, 5), None);
+        assert_eq!(grid.iter().copied().collect::<Vec<_>>(), vec![0, 0, 0, 5]);
+    }
+
+    #[test]
+    fn crop_to_bounds() {
+        let grid = Grid::parse_digits("0000\n0100\n0020\n0000").unwrap();
+
+        let (columns, rows) = grid.bounds(|&cell| cell > 0).unwrap();
+        assert_eq!((columns.clone(), rows.clone()), (1..3, 1..3));
+        assert_eq!(
+            grid.crop(columns, rows),
+            Grid::parse_digits("10\n02").unwrap()
+        );
+        assert_eq!(grid.bounds(|&cell| cell > 5), None);
+    }
+
+    #[test]
+    fn neighbors() {
+        let grid = Grid::new(3, 3, 0);
+
+        assert_eq!(
+            grid.neighbors4(0, 0).collect::<Vec<_>>(),
+            vec![(0, 1), (1, 0)]
+        );
+        assert_eq!(grid.neighbors4(1, 1).count(), 4);
+        assert_eq!(
+            grid.neighbors8(2, 2).collect::<Vec<_>>(),
+            vec![(1, 1), (2, 1), (1, 2)]
+        );
+        assert_eq!(grid.neighbors8(1, 1).count(), 8);
+    }
+}
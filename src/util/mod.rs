@@ -1 +1,4 @@
+pub mod geometry;
+pub mod grid;
 pub mod parse;
+pub mod stream;
@@ -1,13 +1,172 @@
+use pest::error::{Error, LineColLocation};
 use pest::iterators::Pair;
 use pest::RuleType;
+use std::fmt;
 use std::num::ParseIntError;
 
-pub fn token_to_number<T, R>(token: Pair<R>, rule: R) -> Result<T, ParseIntError>
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// The input does not match the day's grammar.
+    Syntax {
+        line: usize,
+        column: usize,
+        message: String,
+        /// Pest's full report, with the expected rules and the offending
+        /// line marked. Positions in it are relative to the parsed text.
+        /// `None` for errors found after parsing.
+        rendered: Option<String>,
+    },
+    /// A number token does not fit into the target integer type.
+    Number {
+        line: usize,
+        column: usize,
+        source: ParseIntError,
+    },
+    /// A grid row has a different length than the first row.
+    GridShape {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+    /// A list of values has the wrong number of elements.
+    Count {
+        line: usize,
+        column: usize,
+        expected: usize,
+        found: usize,
+    },
+    /// The grammar produced a token the generator does not know about.
+    UnexpectedRule {
+        line: usize,
+        column: usize,
+        rule: String,
+    },
+}
+
+impl ParseError {
+    pub fn unexpected_rule<R: RuleType>(token: &Pair<R>) -> ParseError {
+        let (line, column) = token.line_col();
+        ParseError::UnexpectedRule {
+            line,
+            column,
+            rule: format!("{:?}", token.as_rule()),
+        }
+    }
+
+    pub fn count<R: RuleType>(token: &Pair<R>, expected: usize, found: usize) -> ParseError {
+        let (line, column) = token.line_col();
+        ParseError::Count {
+            line,
+            column,
+            expected,
+            found,
+        }
+    }
+
+    /// Moves the error to `line`, for errors found while parsing one line of
+    /// a larger input on its own.
+    pub fn at_line(mut self, line: usize) -> ParseError {
+        match &mut self {
+            ParseError::Syntax { line: old, .. }
+            | ParseError::Number { line: old, .. }
+            | ParseError::GridShape { line: old, .. }
+            | ParseError::Count { line: old, .. }
+            | ParseError::UnexpectedRule { line: old, .. } => *old = line,
+        }
+        self
+    }
+
+    pub fn line_col(&self) -> (usize, usize) {
+        match self {
+            ParseError::Syntax { line, column, .. }
+            | ParseError::Number { line, column, .. }
+            | ParseError::Count { line, column, .. }
+            | ParseError::UnexpectedRule { line, column, .. } => (*line, *column),
+            ParseError::GridShape { line, .. } => (*line, 1),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::Syntax {
+                line,
+                column,
+                message,
+                ..
+            } => write!(f, "{}:{}: syntax error: {}", line, column, message),
+            ParseError::Number {
+                line,
+                column,
+                source,
+            } => write!(f, "{}:{}: invalid number: {}", line, column, source),
+            ParseError::GridShape {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{}: grid row has {} cells, expected {}",
+                line, found, expected
+            ),
+            ParseError::Count {
+                line,
+                column,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{}:{}: found {} values, expected {}",
+                line, column, found, expected
+            ),
+            ParseError::UnexpectedRule { line, column, rule } => {
+                write!(f, "{}:{}: unexpected token {}", line, column, rule)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ParseError::Number { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl<R: RuleType> From<Error<R>> for ParseError {
+    fn from(error: Error<R>) -> ParseError {
+        let (line, column) = match error.line_col {
+            LineColLocation::Pos(pos) => pos,
+            LineColLocation::Span(start, _end) => start,
+        };
+
+        ParseError::Syntax {
+            line,
+            column,
+            message: error.variant.message().into_owned(),
+            rendered: Some(error.to_string()),
+        }
+    }
+}
+
+pub fn token_to_number<T, R>(token: Pair<R>, rule: R) -> Result<T, ParseError>
 where
     T: num::Integer + std::fmt::Display + std::str::FromStr<Err = ParseIntError>,
-    <T as std::str::FromStr>::Err: std::fmt::Debug,
     R: RuleType,
 {
-    assert_eq!(token.as_rule(), rule);
-    token.as_str().parse::<T>()
+    if token.as_rule() != rule {
+        return Err(ParseError::unexpected_rule(&token));
+    }
+
+    token.as_str().parse::<T>().map_err(|source| {
+        let (line, column) = token.line_col();
+        ParseError::Number {
+            line,
+            column,
+            source,
+        }
+    })
 }
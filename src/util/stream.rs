@@ -0,0 +1,81 @@
+use std::fmt;
+use std::io::{self, BufRead};
+
+use crate::util::parse::ParseError;
+
+#[derive(Debug)]
+pub enum StreamError {
+    Io(io::Error),
+    Parse(ParseError),
+}
+
+impl fmt::Display for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StreamError::Io(error) => write!(f, "read error: {}", error),
+            StreamError::Parse(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for StreamError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StreamError::Io(error) => Some(error),
+            StreamError::Parse(error) => Some(error),
+        }
+    }
+}
+
+impl From<io::Error> for StreamError {
+    fn from(error: io::Error) -> StreamError {
+        StreamError::Io(error)
+    }
+}
+
+impl From<ParseError> for StreamError {
+    fn from(error: ParseError) -> StreamError {
+        StreamError::Parse(error)
+    }
+}
+
+/// Parses `reader` one line at a time without holding more than the current
+/// line in memory. `parse` sees a single line, so errors are moved to the
+/// line's real number.
+pub fn parse_lines<T, F>(
+    reader: impl BufRead,
+    mut parse: F,
+) -> impl Iterator<Item = Result<T, StreamError>>
+where
+    F: FnMut(&str) -> Result<T, ParseError>,
+{
+    reader.lines().enumerate().map(move |(index, line)| {
+        let line = line?;
+        parse(&line).map_err(|error| error.at_line(index + 1).into())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_lines_numbers_errors() {
+        let input = "1\n2\nx\n".as_bytes();
+        let results: Vec<_> = parse_lines(input, |line| {
+            line.parse::<u8>().map_err(|source| ParseError::Number {
+                line: 1,
+                column: 1,
+                source,
+            })
+        })
+        .collect();
+
+        assert_eq!(results.len(), 3);
+        assert!(matches!(results[1], Ok(2)));
+        assert!(matches!(
+            results[2],
+            Err(StreamError::Parse(ParseError::Number { line: 3, .. }))
+        ));
+    }
+}
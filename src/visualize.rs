@@ -0,0 +1,447 @@
+use std::collections::BTreeSet;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::thread;
+use std::time::Duration;
+
+use crate::day09;
+use crate::day11::OctopusSim;
+use crate::util::grid::Grid;
+
+/// 256-colour palette indices used to tell basins apart. Regions are
+/// coloured so that neighbours differ, which needs far fewer colours than
+/// there are regions.
+const REGION_COLOURS: [u8; 12] = [27, 34, 124, 136, 91, 30, 166, 62, 100, 161, 37, 130];
+
+const FLASH_COLOUR: u8 = 226;
+const WALL_COLOUR: u8 = 240;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cell {
+    Plain(char),
+    Highlight(char),
+    Wall(char),
+    /// Cell of a region, e.g. a basin, in the numbered colour. Neighbouring
+    /// regions get different numbers.
+    Region(usize, char),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub title: String,
+    pub cells: Grid<Cell>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    /// Coloured output that redraws the terminal in place.
+    Ansi,
+    /// Uncoloured frames separated by blank lines, for logs and snapshots.
+    Plain,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Animation {
+    pub style: Style,
+    pub delay: Duration,
+}
+
+impl Animation {
+    pub fn ansi(delay: Duration) -> Animation {
+        Animation {
+            style: Style::Ansi,
+            delay,
+        }
+    }
+
+    pub fn plain() -> Animation {
+        Animation {
+            style: Style::Plain,
+            delay: Duration::ZERO,
+        }
+    }
+
+    pub fn render(&self, frame: &Frame) -> String {
+        let mut output = String::new();
+
+        if self.style == Style::Ansi {
+            output.push_str("\x1b[2J\x1b[H");
+        }
+
+        output.push_str(&frame.title);
+        output.push('\n');
+
+        for row in frame.cells.rows() {
+            for &cell in row {
+                match self.style {
+                    Style::Ansi => push_ansi_cell(&mut output, cell),
+                    Style::Plain => output.push(plain_symbol(cell)),
+                }
+            }
+            output.push('\n');
+        }
+
+        output
+    }
+
+    pub fn play<W, I>(&self, frames: I, out: &mut W) -> io::Result<()>
+    where
+        W: Write,
+        I: IntoIterator<Item = Frame>,
+    {
+        for (i, frame) in frames.into_iter().enumerate() {
+            if i > 0 {
+                if self.style == Style::Plain {
+                    writeln!(out)?;
+                }
+                thread::sleep(self.delay);
+            }
+
+            out.write_all(self.render(&frame).as_bytes())?;
+            out.flush()?;
+        }
+
+        Ok(())
+    }
+}
+
+fn plain_symbol(cell: Cell) -> char {
+    match cell {
+        Cell::Plain(symbol) | Cell::Highlight(symbol) | Cell::Wall(symbol) => symbol,
+        Cell::Region(region, _symbol) => (b'a' + (region % 26) as u8) as char,
+    }
+}
+
+fn region_colour(region: usize) -> u8 {
+    // Regions with many neighbours fall back to the 6×6×6 colour cube.
+    match REGION_COLOURS.get(region) {
+        Some(&colour) => colour,
+        None => 16 + ((region - REGION_COLOURS.len()) % 216) as u8,
+    }
+}
+
+fn push_ansi_cell(output: &mut String, cell: Cell) {
+    match cell {
+        Cell::Plain(symbol) => output.push(symbol),
+        Cell::Highlight(symbol) => {
+            write!(output, "\x1b[1;38;5;{}m{}\x1b[0m", FLASH_COLOUR, symbol).unwrap()
+        }
+        Cell::Wall(symbol) => {
+            write!(output, "\x1b[38;5;{}m{}\x1b[0m", WALL_COLOUR, symbol).unwrap()
+        }
+        Cell::Region(region, symbol) => write!(
+            output,
+            "\x1b[97;48;5;{}m{}\x1b[0m",
+            region_colour(region),
+            symbol
+        )
+        .unwrap(),
+    }
+}
+
+fn digit(value: u8) -> char {
+    char::from_digit(value as u32, 10).unwrap_or('#')
+}
+
+/// Day 11 energy levels before the first step and after each of `steps`
+/// steps, with the octopuses that flashed highlighted.
+pub fn octopus_frames(grid: Grid<u8>, steps: usize) -> impl Iterator<Item = Frame> {
+    let initial = Frame {
+        title: "Step 0".to_string(),
+        cells: grid.map(|&energy| Cell::Plain(digit(energy))),
+    };
+
+    let step_frames = OctopusSim::new(grid).take(steps).map(|step| {
+        let mut cells = step.grid.map(|&energy| Cell::Plain(digit(energy)));
+        for &coord in &step.flashes {
+            cells[coord] = Cell::Highlight(digit(step.grid[coord]));
+        }
+
+        Frame {
+            title: format!("Step {}: {} flashes", step.number, step.flashes.len()),
+            cells,
+        }
+    });
+
+    std::iter::once(initial).chain(step_frames)
+}
+
+/// Greedy colouring of the basins in discovery order, so that basins next to
+/// the same wall cell, diagonally included, never share a colour. Returns the
+/// colour of each basin, indexed like the basin sizes.
+fn basin_colours(basin_grid: &Grid<usize>, basins: usize) -> Vec<usize> {
+    let mut neighbours = vec![BTreeSet::new(); basins];
+
+    for (x, y) in basin_grid.coordinates() {
+        if basin_grid[(x, y)] != day09::WALL {
+            continue;
+        }
+
+        let touching: BTreeSet<usize> = basin_grid
+            .neighbors8(x, y)
+            .map(|coord| basin_grid[coord])
+            .filter(|&basin| basin != day09::WALL)
+            .collect();
+        for &a in &touching {
+            neighbours[a - 1].extend(touching.iter().filter(|&&b| b != a).map(|b| b - 1));
+        }
+    }
+
+    let mut colours = vec![0; basins];
+    for basin in 0..basins {
+        let taken: BTreeSet<usize> = neighbours[basin]
+            .range(..basin)
+            .map(|&earlier| colours[earlier])
+            .collect();
+        colours[basin] = (0..).find(|colour| !taken.contains(colour)).unwrap();
+    }
+
+    colours
+}
+
+/// Day 9 heightmap with basins filled one at a time in the order `part2`
+/// discovers them.
+pub fn basin_frames(heights: &Grid<u8>) -> Vec<Frame> {
+    let (basin_grid, basin_sizes) = day09::fill_basins(heights);
+    let colours = basin_colours(&basin_grid, basin_sizes.len());
+
+    let frame = |filled: usize, title: String| {
+        let mut cells = heights.map(|&height| Cell::Plain(digit(height)));
+        for coord in heights.coordinates() {
+            let basin = basin_grid[coord];
+            cells[coord] = if basin == day09::WALL {
+                Cell::Wall(digit(heights[coord]))
+            } else if basin <= filled {
+                Cell::Region(colours[basin - 1], digit(heights[coord]))
+            } else {
+                cells[coord]
+            };
+        }
+
+        Frame { title, cells }
+    };
+
+    let mut frames = vec![frame(0, format!("Basins: 0 of {}", basin_sizes.len()))];
+
+    for (i, size) in basin_sizes.iter().enumerate() {
+        frames.push(frame(
+            i + 1,
+            format!("Basins: {} of {} (size {})", i + 1, basin_sizes.len(), size),
+        ));
+    }
+
+    frames
+}
+
+/// Day 5 vent coverage in the puzzle's notation: `.` for no vents, otherwise
+/// the number of overlapping vents, `#` for ten or more.
+pub fn heatmap_ascii(coverage: &Grid<u32>) -> String {
+    let mut output = String::new();
+
+    for row in coverage.rows() {
+        for &count in row {
+            output.push(match count {
+                0 => '.',
+                count => char::from_digit(count, 10).unwrap_or('#'),
+            });
+        }
+        output.push('\n');
+    }
+
+    output
+}
+
+/// Writes the coverage as a binary greyscale PGM image, the brightest value
+/// being the highest count.
+pub fn write_pgm<W: Write>(coverage: &Grid<u32>, out: &mut W) -> io::Result<()> {
+    let max = coverage
+        .iter()
+        .copied()
+        .max()
+        .unwrap_or(0)
+        .clamp(1, u16::MAX as u32);
+
+    writeln!(
+        out,
+        "P5\n{} {}\n{}",
+        coverage.width(),
+        coverage.height(),
+        max
+    )?;
+
+    // Samples take two bytes, most significant first, once the maximum does
+    // not fit into one.
+    let samples: Vec<u8> = if max < 256 {
+        coverage.iter().map(|&count| count as u8).collect()
+    } else {
+        coverage
+            .iter()
+            .flat_map(|&count| (count.min(max) as u16).to_be_bytes())
+            .collect()
+    };
+    out.write_all(&samples)
+}
+
+/// Black for no vents, then from blue through red to yellow as the count
+/// approaches `max`.
+fn heat_colour(count: u32, max: u32) -> [u8; 3] {
+    if count == 0 {
+        return [0, 0, 0];
+    }
+
+    let heat = count as f64 / max as f64;
+    let channel = |value: f64| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+
+    [
+        channel(heat * 2.0),
+        channel(heat * 2.0 - 1.0),
+        channel(1.0 - heat * 2.0),
+    ]
+}
+
+/// Writes the coverage as a binary PPM image coloured by `heat_colour`.
+pub fn write_ppm<W: Write>(coverage: &Grid<u32>, out: &mut W) -> io::Result<()> {
+    let max = coverage.iter().copied().max().unwrap_or(0).max(1);
+
+    writeln!(out, "P6\n{} {}\n255", coverage.width(), coverage.height())?;
+
+    let samples: Vec<u8> = coverage
+        .iter()
+        .flat_map(|&count| heat_colour(count, max))
+        .collect();
+    out.write_all(&samples)
+}
+
+/// Crops the coverage to the smallest box holding every vent. Grids without
+/// vents are returned whole.
+pub fn crop_to_vents(coverage: &Grid<u32>) -> Grid<u32> {
+    match coverage.bounds(|&count| count > 0) {
+        Some((columns, rows)) => coverage.crop(columns, rows),
+        None => coverage.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day05::{self, Orientations, Rasterization};
+
+    fn plain_output(frames: impl IntoIterator<Item = Frame>) -> String {
+        let mut output = vec![];
+        Animation::plain().play(frames, &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn octopus_plain() {
+        let grid = Grid::parse_digits("11111\n19991\n19191\n19991\n11111").unwrap();
+
+        assert_eq!(
+            plain_output(octopus_frames(grid, 1)),
+            "Step 0\n11111\n19991\n19191\n19991\n11111\n\n\
+             Step 1: 9 flashes\n34543\n40004\n50005\n40004\n34543\n"
+        );
+    }
+
+    #[test]
+    fn basin_plain() {
+        let grid = Grid::parse_digits("2199\n3989\n9856").unwrap();
+
+        assert_eq!(
+            plain_output(basin_frames(&grid)),
+            "Basins: 0 of 2\n2199\n3989\n9856\n\n\
+             Basins: 1 of 2 (size 3)\naa99\na989\n9856\n\n\
+             Basins: 2 of 2 (size 4)\naa99\na9b9\n9bbb\n"
+        );
+    }
+
+    const VENTS_EXAMPLE: &str = "\
+0,9 -> 5,9
+8,0 -> 0,8
+9,4 -> 3,4
+2,2 -> 2,1
+7,0 -> 7,4
+6,4 -> 2,0
+0,9 -> 2,9
+3,4 -> 1,4
+0,0 -> 8,8
+5,5 -> 8,2";
+
+    fn vents_coverage() -> Grid<u32> {
+        let lines = day05::parse_input(VENTS_EXAMPLE).unwrap();
+        day05::coverage_grid(&lines, Orientations::STRAIGHT, Rasterization::Strict).unwrap()
+    }
+
+    #[test]
+    fn vents_ascii() {
+        assert_eq!(
+            heatmap_ascii(&vents_coverage()),
+            "1.1....11.\n.111...2..\n..2.1.111.\n...1.2.2..\n.112313211\n\
+             ...1.2....\n..1...1...\n.1.....1..\n1.......1.\n222111....\n"
+        );
+
+        let lines = day05::parse_input("3,2 -> 5,2\n4,1 -> 4,3").unwrap();
+        let coverage =
+            day05::coverage_grid(&lines, Orientations::AXES, Rasterization::Strict).unwrap();
+        assert_eq!(heatmap_ascii(&crop_to_vents(&coverage)), ".1.\n121\n.1.\n");
+    }
+
+    #[test]
+    fn vents_images() {
+        let coverage = Grid::from_vec(2, vec![0, 1, 3, 300]);
+
+        let mut pgm = vec![];
+        write_pgm(&coverage, &mut pgm).unwrap();
+        assert_eq!(pgm, b"P5\n2 2\n300\n\x00\x00\x00\x01\x00\x03\x01\x2c");
+
+        let mut ppm = vec![];
+        write_ppm(&vents_coverage(), &mut ppm).unwrap();
+        assert!(ppm.starts_with(b"P6\n10 10\n255\n"));
+        assert_eq!(ppm.len(), 13 + 10 * 10 * 3);
+        assert_eq!(heat_colour(0, 3), [0, 0, 0]);
+        assert_eq!(heat_colour(3, 3), [255, 255, 0]);
+    }
+
+    #[test]
+    fn basin_colouring() {
+        let row = "1919191919191";
+        let wall = "9999999999999";
+        let grid = Grid::parse_digits(&[row, wall, row, wall, row].join("\n")).unwrap();
+        let (basin_grid, basin_sizes) = day09::fill_basins(&grid);
+
+        let colours = basin_colours(&basin_grid, basin_sizes.len());
+        assert_eq!(colours.len(), 21);
+        assert!(colours.iter().all(|&colour| colour < 4));
+
+        // Every basin is a single cell, so all colours next to a wall differ.
+        let walls = basin_grid
+            .coordinates()
+            .filter(|&coord| basin_grid[coord] == day09::WALL);
+        for (x, y) in walls {
+            let touching: BTreeSet<usize> = basin_grid
+                .neighbors8(x, y)
+                .map(|coord| basin_grid[coord])
+                .filter(|&basin| basin != day09::WALL)
+                .map(|basin| colours[basin - 1])
+                .collect();
+            let basins = basin_grid
+                .neighbors8(x, y)
+                .filter(|&coord| basin_grid[coord] != day09::WALL)
+                .count();
+            assert_eq!(touching.len(), basins, "({}, {})", x, y);
+        }
+    }
+
+    #[test]
+    fn ansi_highlight() {
+        let frame = Frame {
+            title: "t".to_string(),
+            cells: Grid::from_vec(2, vec![Cell::Plain('1'), Cell::Highlight('0')]),
+        };
+
+        assert_eq!(
+            Animation::ansi(Duration::ZERO).render(&frame),
+            "\x1b[2J\x1b[Ht\n1\x1b[1;38;5;226m0\x1b[0m\n"
+        );
+    }
+}